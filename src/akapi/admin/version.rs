@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::akapi::{AkApiRoute, AkClient};
+
+pub struct GetVersion;
+
+#[async_trait]
+impl AkApiRoute for GetVersion {
+    type Body = ();
+    type Response = GetVersionResponse;
+    type Error = GetVersionError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, _body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak.get("/api/v3/admin/version/").send().await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: GetVersionResponse = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::FORBIDDEN => Err(Self::Error::Forbidden),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GetVersionResponse {
+    pub version_current: String,
+    pub version_latest: String,
+    pub build_hash: String,
+    pub outdated: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum GetVersionError {
+    #[error("Server denied our authentication.")]
+    Forbidden,
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use reqwest::StatusCode;
 use thiserror::Error;
 
-use crate::akapi::{types::Application, AkApiRoute, AkClient};
+use crate::akapi::{types::Application, validation::ValidationErrors, AkApiRoute, AkClient};
 
 pub struct CreateApplication;
 
@@ -26,6 +26,9 @@ impl AkApiRoute for CreateApplication {
 
                 Ok(body)
             }
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
@@ -36,6 +39,8 @@ impl AkApiRoute for CreateApplication {
 
 #[derive(Error, Debug)]
 pub enum CreateApplicationError {
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
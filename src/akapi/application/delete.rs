@@ -21,7 +21,7 @@ impl AkApiRoute for DeleteApplication {
 
         match res.status() {
             StatusCode::NO_CONTENT => Ok(()),
-            StatusCode::BAD_REQUEST => Err(Self::Error::NotFound),
+            StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
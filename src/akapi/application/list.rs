@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{pagination::Paginated, types::Application, AkApiRoute, AkClient};
+
+pub struct ListApplications;
+
+#[async_trait]
+impl AkApiRoute for ListApplications {
+    type Body = ListApplicationsBody;
+    type Response = Vec<Application>;
+    type Error = ListApplicationsError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let mut applications = Vec::new();
+        let mut page = Some(1);
+
+        while let Some(current) = page {
+            let mut query = vec![
+                ("page", current.to_string()),
+                ("page_size", "100".to_string()),
+                // Without this only the applications the user has access to are returned.
+                ("superuser_full_list", "true".to_string()),
+            ];
+            // The API has no prefix filter, but the search narrows down the results to filter.
+            if let Some(prefix) = &body.slug_prefix {
+                query.push(("search", prefix.clone()));
+            }
+
+            let res = ak
+                .get("/api/v3/core/applications/")
+                .query(&query)
+                .send()
+                .await?;
+
+            match res.status() {
+                StatusCode::OK => {
+                    let body: Paginated<Application> = res.json().await?;
+
+                    page = body.next_page();
+                    applications.extend(body.results);
+                }
+                code => {
+                    return Err(Self::Error::Unknown(format!(
+                        "Invalid status code {}",
+                        code
+                    )))
+                }
+            }
+        }
+
+        if let Some(prefix) = &body.slug_prefix {
+            applications.retain(|application| application.slug.starts_with(prefix));
+        }
+
+        Ok(applications)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ListApplicationsBody {
+    pub slug_prefix: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum ListApplicationsError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -1,9 +1,11 @@
 mod create;
 mod delete;
 mod get;
+mod list;
 mod patch;
 
 pub use create::*;
 pub use delete::*;
 pub use get::*;
+pub use list::*;
 pub use patch::*;
@@ -1,6 +1,9 @@
 use anyhow::{anyhow, Result};
 use k8s_openapi::api::core::v1::Secret;
 use kube::{Api, Client};
+use sha2::{Digest, Sha256};
+
+use crate::resources::{authentik::crd::Authentik, credentials_namespace};
 
 use super::{
     user::{GetSelf, GetSelfError},
@@ -9,6 +12,33 @@ use super::{
 
 pub static TEMP_AUTH_TOKEN: &str = "AUTHENTIK_TEMP_AUTH_TOKEN";
 
+// Get the namespace and name of the secret holding the token created by the operator.
+// In a central namespace the secrets of all instances are together, so the name includes the
+// namespace, and a hash of both as dashes in these would make names like `a-b` + `c` and `a` + `b-c`
+// collide. This applies to the instances in the central namespace itself as well.
+pub fn operator_token_secret(ns: &str, instance: &str) -> (String, String) {
+    match credentials_namespace() {
+        Some(central) => {
+            let digest = Sha256::digest(format!("{}/{}", ns, instance).as_bytes());
+            let hash = format!("{:x}", digest);
+            (
+                central,
+                format!("ak-{}-{}-{}-api-operatortoken", ns, instance, &hash[..8]),
+            )
+        }
+        None => (ns.to_string(), format!("ak-{}-api-operatortoken", instance)),
+    }
+}
+
+// The name older versions used in a central namespace, which is still read so the operator can
+// authenticate to store the token under the new name.
+fn legacy_operator_token_secret(ns: &str, instance: &str) -> Option<String> {
+    credentials_namespace().map(|central| match central == ns {
+        true => format!("ak-{}-api-operatortoken", instance),
+        false => format!("ak-{}-{}-api-operatortoken", ns, instance),
+    })
+}
+
 pub async fn get_valid_token(client: Client, ns: &str, instance: &str) -> Result<String> {
     // Try a token in the secret first.
     if let Some(secret) = get_valid_secret_token(client, ns, instance).await? {
@@ -43,22 +73,50 @@ pub async fn get_valid_secret_token(
 async fn validate_token(ak: &AkClient) -> Result<bool> {
     match GetSelf::send(ak, ()).await {
         Ok(_) => Ok(true),
-        Err(GetSelfError::Forbidden) => Ok(false),
+        Err(GetSelfError::Forbidden | GetSelfError::Unauthorized) => Ok(false),
         Err(e) => Err(e.into()),
     }
 }
 
+// Check the token from `apiTokenSecretRef`, as a rejected token would otherwise silently fall back to
+// the temporary token.
+pub async fn check_secret_token(client: Client, ns: &str, instance: &str) -> Result<()> {
+    let token = get_token_secret(client, ns, instance)
+        .await?
+        .ok_or(anyhow!(
+            "The secret from `apiTokenSecretRef` does not exist."
+        ))?;
+    let ak = AkClient::new(&token, instance, ns)?;
+
+    match GetSelf::send(&ak, ()).await {
+        Ok(_) => Ok(()),
+        Err(e @ (GetSelfError::Forbidden | GetSelfError::Unauthorized)) => Err(anyhow!(
+            "The API token from `apiTokenSecretRef` is invalid or expired ({}).",
+            e
+        )),
+        // Authentik might not be available yet, which is reported by the migration check.
+        Err(_) => Ok(()),
+    }
+}
+
 async fn get_token_secret(client: Client, ns: &str, instance: &str) -> Result<Option<String>> {
+    let (secret_ns, name, key) = get_token_secret_ref(client.clone(), ns, instance).await?;
+
     // Get the token from a secret, iff it exists.
-    let api: Api<Secret> = Api::namespaced(client, ns);
-    let name = format!("ak-{}-api-operatortoken", instance);
+    let api: Api<Secret> = Api::namespaced(client, &secret_ns);
+    let mut secret = api.get_opt(&name).await?;
+    if secret.is_none() && name == operator_token_secret(ns, instance).1 {
+        if let Some(legacy) = legacy_operator_token_secret(ns, instance) {
+            secret = api.get_opt(&legacy).await?;
+        }
+    }
 
-    if let Some(secret) = api.get_opt(&name).await? {
+    if let Some(secret) = secret {
         let mut data = secret
             .data
             .ok_or(anyhow!("Token secret does not contain any data"))?;
         let token = data
-            .remove("token")
+            .remove(&key)
             .ok_or(anyhow!("Token secret does not contain a token."))?;
         let token_string = String::from_utf8(token.0)?;
 
@@ -67,3 +125,24 @@ async fn get_token_secret(client: Client, ns: &str, instance: &str) -> Result<Op
         Ok(None)
     }
 }
+
+async fn get_token_secret_ref(
+    client: Client,
+    ns: &str,
+    instance: &str,
+) -> Result<(String, String, String)> {
+    // Use the pre-created token secret if the instance has one configured.
+    let api: Api<Authentik> = Api::namespaced(client, ns);
+    let secret_ref = api
+        .get_opt(instance)
+        .await?
+        .and_then(|ak| ak.spec.api_token_secret_ref);
+
+    Ok(match secret_ref {
+        Some(secret_ref) => (ns.to_string(), secret_ref.name, secret_ref.key),
+        None => {
+            let (secret_ns, name) = operator_token_secret(ns, instance);
+            (secret_ns, name, "token".to_string())
+        }
+    })
+}
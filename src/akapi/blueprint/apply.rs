@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{types::BlueprintInstance, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct ApplyBlueprint;
+
+#[async_trait]
+impl AkApiRoute for ApplyBlueprint {
+    type Body = String;
+    type Response = BlueprintInstance;
+    type Error = ApplyBlueprintError;
+
+    // Apply the blueprint right away, instead of waiting for the next scheduled run of the worker.
+    #[instrument]
+    async fn send(ak: &AkClient, pk: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .post(&format!("/api/v3/managed/blueprints/{}/apply/", pk))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: BlueprintInstance = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ApplyBlueprintError {
+    #[error("The given blueprint instance was not found.")]
+    NotFound,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::akapi::{types::BlueprintInstance, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct CreateBlueprint;
+
+#[async_trait]
+impl AkApiRoute for CreateBlueprint {
+    type Body = CreateBlueprintBody;
+    type Response = BlueprintInstance;
+    type Error = CreateBlueprintError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .post("/api/v3/managed/blueprints/")
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::CREATED => {
+                let body: BlueprintInstance = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateBlueprintBody {
+    pub name: String,
+    // The path of the blueprint, relative to the blueprints directory of the worker.
+    pub path: String,
+    // The blueprint itself, for blueprints stored by Authentik instead of read from a file.
+    pub content: String,
+    pub context: Value,
+    pub enabled: bool,
+}
+
+#[derive(Error, Debug)]
+pub enum CreateBlueprintError {
+    #[error("The blueprint instance already exists!")]
+    ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
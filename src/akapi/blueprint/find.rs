@@ -0,0 +1,57 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{pagination::Paginated, types::BlueprintInstance, AkApiRoute, AkClient};
+
+pub struct FindBlueprint;
+
+#[async_trait]
+impl AkApiRoute for FindBlueprint {
+    type Body = ();
+    type Response = Vec<BlueprintInstance>;
+    type Error = FindBlueprintError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, _body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let mut instances = Vec::new();
+        let mut page = Some(1);
+
+        while let Some(current) = page {
+            let query = vec![
+                ("page", current.to_string()),
+                ("page_size", "100".to_string()),
+            ];
+            let res = ak
+                .get("/api/v3/managed/blueprints/")
+                .query(&query)
+                .send()
+                .await?;
+
+            match res.status() {
+                StatusCode::OK => {
+                    let body: Paginated<BlueprintInstance> = res.json().await?;
+
+                    page = body.next_page();
+                    instances.extend(body.results);
+                }
+                code => {
+                    return Err(Self::Error::Unknown(format!(
+                        "Invalid status code {}",
+                        code
+                    )))
+                }
+            }
+        }
+
+        Ok(instances)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FindBlueprintError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -0,0 +1,9 @@
+mod apply;
+mod create;
+mod find;
+mod patch;
+
+pub use apply::*;
+pub use create::*;
+pub use find::*;
+pub use patch::*;
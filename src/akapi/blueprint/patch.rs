@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::akapi::{types::BlueprintInstance, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct PatchBlueprint;
+
+#[async_trait]
+impl AkApiRoute for PatchBlueprint {
+    type Body = PatchBlueprintBody;
+    type Response = BlueprintInstance;
+    type Error = PatchBlueprintError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .patch(&format!("/api/v3/managed/blueprints/{}/", body.pk))
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: BlueprintInstance = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PatchBlueprintBody {
+    #[serde(skip_serializing)]
+    pub pk: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+}
+
+#[derive(Error, Debug)]
+pub enum PatchBlueprintError {
+    #[error("The given blueprint instance was not found.")]
+    NotFound,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
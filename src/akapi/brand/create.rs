@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{types::Brand, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct CreateBrand;
+
+#[async_trait]
+impl AkApiRoute for CreateBrand {
+    type Body = Brand;
+    type Response = Brand;
+    type Error = CreateBrandError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak.post("/api/v3/core/brands/").json(&body).send().await?;
+
+        match res.status() {
+            StatusCode::CREATED => {
+                let body: Brand = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CreateBrandError {
+    #[error("A brand for this domain already exists!")]
+    ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::akapi::{types::Brand, AkApiRoute, AkClient};
+
+pub struct FindBrand;
+
+#[async_trait]
+impl AkApiRoute for FindBrand {
+    type Body = FindBrandBody;
+    type Response = Vec<Brand>;
+    type Error = FindBrandError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let mut query = vec![("page_size", "1000".to_string())];
+
+        if let Some(domain) = body.domain {
+            query.push(("domain", domain));
+        }
+
+        let res = ak.get("/api/v3/core/brands/").query(&query).send().await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: FindBrandResponse = res.json().await?;
+
+                Ok(body.results)
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FindBrandBody {
+    pub domain: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FindBrandResponse {
+    pub results: Vec<Brand>,
+}
+
+#[derive(Error, Debug)]
+pub enum FindBrandError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -0,0 +1,7 @@
+mod create;
+mod find;
+mod patch;
+
+pub use create::*;
+pub use find::*;
+pub use patch::*;
@@ -0,0 +1,51 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{types::Brand, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct PatchBrand;
+
+#[async_trait]
+impl AkApiRoute for PatchBrand {
+    type Body = Brand;
+    type Response = Brand;
+    type Error = PatchBrandError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .patch(&format!("/api/v3/core/brands/{}/", body.brand_uuid))
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: Brand = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum PatchBrandError {
+    #[error("The given brand was not found.")]
+    NotFound,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+
+use super::{
+    flow::{FindFlow, FindFlowBody, FindFlowError},
+    stages::{FindStage, FindStageBody, FindStageError},
+    types::{Flow, Stage},
+    AkApiRoute, AkClient,
+};
+
+// Keeps the lists of flows and stages for the duration of a single reconcile, so the parts that need
+// these share a single request. Calls that change a list have to invalidate it.
+#[derive(Default)]
+pub struct ListCache {
+    flows: Mutex<Option<Arc<Vec<Flow>>>>,
+    stages: Mutex<Option<Arc<Vec<Stage>>>>,
+}
+
+impl ListCache {
+    pub async fn flows(&self, ak: &AkClient) -> Result<Arc<Vec<Flow>>, FindFlowError> {
+        let mut flows = self.flows.lock().await;
+        if let Some(flows) = flows.as_ref() {
+            return Ok(flows.clone());
+        }
+
+        let found = Arc::new(FindFlow::send(ak, FindFlowBody::default()).await?);
+        *flows = Some(found.clone());
+        Ok(found)
+    }
+
+    pub async fn stages(&self, ak: &AkClient) -> Result<Arc<Vec<Stage>>, FindStageError> {
+        let mut stages = self.stages.lock().await;
+        if let Some(stages) = stages.as_ref() {
+            return Ok(stages.clone());
+        }
+
+        let found = Arc::new(FindStage::send(ak, FindStageBody::default()).await?);
+        *stages = Some(found.clone());
+        Ok(found)
+    }
+
+    pub async fn invalidate_flows(&self) {
+        *self.flows.lock().await = None;
+    }
+
+    pub async fn invalidate_stages(&self) {
+        *self.stages.lock().await = None;
+    }
+}
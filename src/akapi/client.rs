@@ -1,48 +1,304 @@
-use std::time::Duration;
+use std::{collections::BTreeMap, fmt, sync::Arc, time::Duration};
 
 use anyhow::Result;
+use lazy_static::lazy_static;
 use reqwest::{
-    header::{HeaderMap, AUTHORIZATION},
-    RequestBuilder,
+    header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION, RETRY_AFTER},
+    Method, RequestBuilder, Response, StatusCode,
 };
+use serde::Serialize;
+use serde_json::Value;
+use tracing::Level;
+
+use super::ratelimit::RateLimiter;
+
+// Fields with a name containing any of these have their value replaced before a body is logged.
+// This is a broad match, so fields of new routes holding credentials are redacted by default.
+static REDACTED_FIELDS: [&str; 6] = ["key", "token", "password", "secret", "link", "session"];
+
+// How often a request is retried when Authentik responds with 429, and the longest wait between these.
+static MAX_RATE_LIMITED_RETRIES: u32 = 3;
+static MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+lazy_static! {
+    // A single client is shared by all instances, so connections are reused between reconciles.
+    pub(super) static ref HTTP_CLIENT: reqwest::Client = build_http_client();
+}
 
-#[derive(Debug)]
 pub struct AkClient {
     client: reqwest::Client,
+    auth: HeaderValue,
     host: String,
+    limiter: Option<Arc<RateLimiter>>,
 }
 
 impl AkClient {
     pub fn new(api_key: &str, instance: &str, namespace: &str) -> Result<Self> {
-        let user_agent = format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
-        let mut headers = HeaderMap::new();
-        headers.insert(AUTHORIZATION, format!("Bearer {}", api_key).parse()?);
-
-        let client = reqwest::Client::builder()
-            .default_headers(headers)
-            .user_agent(user_agent)
-            .timeout(Duration::from_secs(120))
-            .build()?;
+        let mut auth: HeaderValue = format!("Bearer {}", api_key).parse()?;
+        auth.set_sensitive(true);
 
+        let host = format!("authentik-{}.{}", instance, namespace);
         Ok(Self {
-            client,
-            host: format!("authentik-{}.{}", instance, namespace),
+            client: HTTP_CLIENT.clone(),
+            auth,
+            limiter: RateLimiter::for_host(&host),
+            host,
         })
     }
 
-    pub fn get(&self, path: &str) -> RequestBuilder {
-        self.client.get(format!("http://{}{}", self.host, path))
+    pub fn get(&self, path: &str) -> AkRequest {
+        self.request(Method::GET, path)
+    }
+
+    pub fn patch(&self, path: &str) -> AkRequest {
+        self.request(Method::PATCH, path)
+    }
+
+    pub fn post(&self, path: &str) -> AkRequest {
+        self.request(Method::POST, path)
+    }
+
+    pub fn delete(&self, path: &str) -> AkRequest {
+        self.request(Method::DELETE, path)
+    }
+
+    fn request(&self, method: Method, path: &str) -> AkRequest {
+        AkRequest(
+            self.client
+                .request(method, format!("http://{}{}", self.host, path))
+                .header(AUTHORIZATION, self.auth.clone()),
+            self.limiter.clone(),
+        )
+    }
+}
+
+fn build_http_client() -> reqwest::Client {
+    let user_agent = format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+    let max_idle_per_host = std::env::var("AKAPI_POOL_MAX_IDLE_PER_HOST")
+        .ok()
+        .and_then(|max| max.parse().ok())
+        .unwrap_or(8);
+    let idle_timeout = std::env::var("AKAPI_POOL_IDLE_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse().ok())
+        .unwrap_or(90);
+
+    reqwest::Client::builder()
+        .default_headers(extra_headers())
+        .user_agent(user_agent)
+        .timeout(Duration::from_secs(120))
+        .pool_max_idle_per_host(max_idle_per_host)
+        .pool_idle_timeout(Duration::from_secs(idle_timeout))
+        .build()
+        .expect("Failed to build the HTTP client")
+}
+
+// The client holds the API key, so only the host is shown.
+impl fmt::Debug for AkClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AkClient")
+            .field("host", &self.host)
+            .finish()
+    }
+}
+
+// A request to the Authentik API, which logs the request and response when sent.
+pub struct AkRequest(RequestBuilder, Option<Arc<RateLimiter>>);
+
+impl AkRequest {
+    pub fn json<T: Serialize + ?Sized>(self, body: &T) -> Self {
+        Self(self.0.json(body), self.1)
+    }
+
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        Self(self.0.query(query), self.1)
     }
 
-    pub fn patch(&self, path: &str) -> RequestBuilder {
-        self.client.patch(format!("http://{}{}", self.host, path))
+    pub async fn send(self) -> reqwest::Result<Response> {
+        let (client, req) = self.0.build_split();
+        let mut req = req?;
+        let method = req.method().clone();
+        let path = req.url().path().to_string();
+
+        debug!("Sending {} {}", method, path);
+        if enabled!(Level::TRACE) {
+            if let Some(body) = req.body().and_then(|body| body.as_bytes()) {
+                trace!("Request body of {} {}: {}", method, path, redact_body(body));
+            }
+        }
+
+        let mut attempt = 0;
+        let res = loop {
+            if let Some(limiter) = &self.1 {
+                limiter.acquire().await;
+            }
+
+            // Requests with a streaming body cannot be cloned, so these are never retried.
+            let retry = match attempt < MAX_RATE_LIMITED_RETRIES {
+                true => req.try_clone(),
+                false => None,
+            };
+            let res = client.execute(req).await?;
+            debug!("Received {} for {} {}", res.status(), method, path);
+
+            req = match retry {
+                Some(retry) if res.status() == StatusCode::TOO_MANY_REQUESTS => retry,
+                _ => break res,
+            };
+            let delay = retry_after(&res)
+                .unwrap_or_else(|| Duration::from_secs(2u64.pow(attempt)))
+                .min(MAX_RETRY_AFTER);
+            warn!(
+                "Rate limited by Authentik for {} {}, retrying in {:?}",
+                method, path, delay
+            );
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        };
+
+        if !enabled!(Level::TRACE) {
+            return Ok(res);
+        }
+
+        // Reading the body consumes the response, so a new one is built from the parts.
+        let status = res.status();
+        let version = res.version();
+        let headers = res.headers().clone();
+        let body = res.bytes().await?;
+        trace!(
+            "Response body of {} {}: {}",
+            method,
+            path,
+            redact_body(&body)
+        );
+
+        let mut builder = http::Response::builder().status(status).version(version);
+        if let Some(builder_headers) = builder.headers_mut() {
+            *builder_headers = headers;
+        }
+
+        Ok(builder
+            .body(body)
+            .expect("Failed to rebuild the response")
+            .into())
+    }
+}
+
+// The delay requested with the Retry-After header, which is either a number of seconds or a date.
+pub fn retry_after(res: &Response) -> Option<Duration> {
+    let value = res.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (date.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+// Static headers sent with every request, for proxies in front of Authentik that require these.
+// These are given as a JSON object, and cannot replace the API key.
+fn extra_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let raw = match std::env::var("AKAPI_EXTRA_HEADERS") {
+        Ok(raw) if !raw.is_empty() => raw,
+        _ => return headers,
+    };
+
+    let values: BTreeMap<String, String> = match serde_json::from_str(&raw) {
+        Ok(values) => values,
+        Err(e) => {
+            warn!(
+                "Ignoring AKAPI_EXTRA_HEADERS, as it is not a JSON object of strings: {}",
+                e
+            );
+            return headers;
+        }
+    };
+
+    for (name, value) in values {
+        match (name.parse::<HeaderName>(), value.parse::<HeaderValue>()) {
+            (Ok(name), _) if name == AUTHORIZATION => {
+                warn!(
+                    "Ignoring the extra `{}` header, as it holds the API key.",
+                    name
+                );
+            }
+            (Ok(name), Ok(mut value)) => {
+                // These could hold a token for the proxy, so keep these out of the logs.
+                value.set_sensitive(true);
+                headers.insert(name, value);
+            }
+            _ => warn!(
+                "Ignoring the extra `{}` header, as it is not a valid header.",
+                name
+            ),
+        }
     }
 
-    pub fn post(&self, path: &str) -> RequestBuilder {
-        self.client.post(format!("http://{}{}", self.host, path))
+    headers
+}
+
+fn redact_body(body: &[u8]) -> String {
+    match serde_json::from_slice::<Value>(body) {
+        Ok(mut value) => {
+            redact_value(&mut value);
+            value.to_string()
+        }
+        Err(_) => format!("<{} bytes>", body.len()),
     }
+}
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if is_redacted(key) {
+                    *value = Value::String("[redacted]".to_string());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+fn is_redacted(field: &str) -> bool {
+    let field = field.to_lowercase();
+    REDACTED_FIELDS
+        .iter()
+        .any(|redacted| field.contains(redacted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_credentials_by_substring() {
+        let body = serde_json::json!({
+            "pk": 1,
+            "name": "recovery",
+            "link": "https://auth.example.com/recovery/abc",
+            "Client_Secret": "secret",
+            "session_id": "abc",
+            "nested": [{ "api_token": "abc", "username": "akadmin" }],
+        });
+        let redacted: Value =
+            serde_json::from_str(&redact_body(body.to_string().as_bytes())).unwrap();
 
-    pub fn delete(&self, path: &str) -> RequestBuilder {
-        self.client.delete(format!("http://{}{}", self.host, path))
+        assert_eq!(redacted["pk"], 1);
+        assert_eq!(redacted["name"], "recovery");
+        assert_eq!(redacted["nested"][0]["username"], "akadmin");
+        for value in [
+            &redacted["link"],
+            &redacted["Client_Secret"],
+            &redacted["session_id"],
+            &redacted["nested"][0]["api_token"],
+        ] {
+            assert_eq!(value, "[redacted]");
+        }
     }
 }
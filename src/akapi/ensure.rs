@@ -0,0 +1,74 @@
+use std::error::Error;
+
+use anyhow::Result;
+use serde::Serialize;
+
+use super::{AkApiRoute, AkClient};
+
+// What had to be done to get the object into the desired state.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Ensured {
+    Unchanged,
+    Created,
+    Updated,
+    // It didn't exist yet when looked for, but did when creating it, and was updated instead.
+    Adopted,
+}
+
+// Make sure an object exists as desired, using the given routes to find, create and update it.
+// Only the serialized fields are compared, the responses also contain values that cannot be set.
+pub async fn ensure_exists<T, Find, Create, Update>(
+    ak: &AkClient,
+    find: impl Fn() -> Find::Body,
+    is_target: impl Fn(&T) -> bool,
+    desired: T,
+    update: impl Fn(&T, T) -> Update::Body,
+) -> Result<(T, Ensured)>
+where
+    T: Serialize + Clone,
+    Find: AkApiRoute<Response = Vec<T>>,
+    Create: AkApiRoute<Body = T, Response = T>,
+    Update: AkApiRoute<Response = T>,
+    Find::Error: Error + Send + Sync + 'static,
+    Create::Error: Error + Send + Sync + 'static,
+    Update::Error: Error + Send + Sync + 'static,
+{
+    if let Some(existing) = Find::send(ak, find()).await?.into_iter().find(&is_target) {
+        return update_if_changed::<T, Update>(ak, existing, desired, update, Ensured::Updated)
+            .await;
+    }
+
+    let error = match Create::send(ak, desired.clone()).await {
+        Ok(created) => return Ok((created, Ensured::Created)),
+        Err(e) => e,
+    };
+
+    // The object could have been created since it was looked for, for example by a concurrent
+    // reconcile, in which case it is adopted. Otherwise the creation failed for another reason.
+    match Find::send(ak, find()).await?.into_iter().find(&is_target) {
+        Some(existing) => {
+            update_if_changed::<T, Update>(ak, existing, desired, update, Ensured::Adopted).await
+        }
+        None => Err(error.into()),
+    }
+}
+
+async fn update_if_changed<T, Update>(
+    ak: &AkClient,
+    existing: T,
+    desired: T,
+    update: impl Fn(&T, T) -> Update::Body,
+    changed: Ensured,
+) -> Result<(T, Ensured)>
+where
+    T: Serialize,
+    Update: AkApiRoute<Response = T>,
+    Update::Error: Error + Send + Sync + 'static,
+{
+    if serde_json::to_value(&existing)? == serde_json::to_value(&desired)? {
+        return Ok((existing, Ensured::Unchanged));
+    }
+
+    let updated = Update::send(ak, update(&existing, desired)).await?;
+    Ok((updated, changed))
+}
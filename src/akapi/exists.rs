@@ -0,0 +1,26 @@
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::AkClient;
+
+// Check whether the object at the given path exists, for routes that only need to know that.
+pub async fn object_exists(ak: &AkClient, path: &str) -> Result<bool, ObjectExistsError> {
+    let res = ak.get(path).send().await?;
+
+    match res.status() {
+        StatusCode::OK => Ok(true),
+        StatusCode::NOT_FOUND => Ok(false),
+        code => Err(ObjectExistsError::Unknown(format!(
+            "Invalid status code {}",
+            code
+        ))),
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum ObjectExistsError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
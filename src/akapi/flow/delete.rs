@@ -1,8 +1,7 @@
 use async_trait::async_trait;
-use reqwest::StatusCode;
 use thiserror::Error;
 
-use crate::akapi::{AkApiRoute, AkClient};
+use crate::akapi::{classify_status, AkApiRoute, AkClient, AkError};
 
 pub struct DeleteFlow;
 
@@ -19,13 +18,11 @@ impl AkApiRoute for DeleteFlow {
             .send()
             .await?;
 
-        match res.status() {
-            StatusCode::NO_CONTENT => Ok(()),
-            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+        match classify_status(res).await {
+            Ok(_) => Ok(()),
+            Err(AkError::NotFound) => Err(Self::Error::NotFound),
+            Err(AkError::ConnectionError(e)) => Err(Self::Error::ConnectionError(e)),
+            Err(e) => Err(Self::Error::Unknown(e.to_string())),
         }
     }
 }
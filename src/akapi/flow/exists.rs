@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use crate::akapi::{
+    exists::{object_exists, ObjectExistsError},
+    AkApiRoute, AkClient,
+};
+
+pub struct FlowExists;
+
+#[async_trait]
+impl AkApiRoute for FlowExists {
+    type Body = String;
+    type Response = bool;
+    type Error = ObjectExistsError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, slug: Self::Body) -> Result<Self::Response, Self::Error> {
+        object_exists(ak, &format!("/api/v3/flows/instances/{}/", slug)).await
+    }
+}
@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::akapi::{types::Flow, AkApiRoute, AkClient};
+
+pub struct FindFlow;
+
+#[async_trait]
+impl AkApiRoute for FindFlow {
+    type Body = FindFlowBody;
+    type Response = Vec<Flow>;
+    type Error = FindFlowError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let mut query = vec![("page_size", "1000".to_string())];
+
+        if let Some(slug) = body.slug {
+            query.push(("slug", slug));
+        }
+
+        let res = ak
+            .get("/api/v3/flows/instances/")
+            .query(&query)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: FindFlowResponse = res.json().await?;
+
+                Ok(body.results)
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct FindFlowBody {
+    pub slug: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FindFlowResponse {
+    pub results: Vec<Flow>,
+}
+
+#[derive(Error, Debug)]
+pub enum FindFlowError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
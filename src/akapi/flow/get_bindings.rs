@@ -0,0 +1,62 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{pagination::Paginated, types::FlowStageBinding, AkApiRoute, AkClient};
+
+pub struct GetFlowBindings;
+
+#[async_trait]
+impl AkApiRoute for GetFlowBindings {
+    type Body = String;
+    type Response = Vec<FlowStageBinding>;
+    type Error = GetFlowBindingsError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, flow_pk: Self::Body) -> Result<Self::Response, Self::Error> {
+        let mut bindings = Vec::new();
+        let mut page = Some(1);
+
+        while let Some(current) = page {
+            let query = vec![
+                ("target", flow_pk.clone()),
+                ("ordering", "order".to_string()),
+                ("page", current.to_string()),
+                ("page_size", "100".to_string()),
+            ];
+            let res = ak
+                .get("/api/v3/flows/bindings/")
+                .query(&query)
+                .send()
+                .await?;
+
+            match res.status() {
+                StatusCode::OK => {
+                    let body: Paginated<FlowStageBinding> = res.json().await?;
+
+                    page = body.next_page();
+                    bindings.extend(body.results);
+                }
+                code => {
+                    return Err(Self::Error::Unknown(format!(
+                        "Invalid status code {}",
+                        code
+                    )))
+                }
+            }
+        }
+
+        // The order is what matters for the flow, so don't rely on the API for it.
+        bindings.sort_by_key(|binding| binding.order);
+
+        Ok(bindings)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum GetFlowBindingsError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
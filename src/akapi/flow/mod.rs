@@ -1,5 +1,13 @@
 mod delete;
+mod exists;
+mod find;
 mod get;
+mod get_bindings;
+mod patch;
 
 pub use delete::*;
+pub use exists::*;
+pub use find::*;
 pub use get::*;
+pub use get_bindings::*;
+pub use patch::*;
@@ -0,0 +1,68 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::akapi::{types::Flow, AkApiRoute, AkClient};
+
+pub struct PatchFlow;
+
+#[async_trait]
+impl AkApiRoute for PatchFlow {
+    type Body = PatchFlowBody;
+    type Response = Flow;
+    type Error = PatchFlowError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .patch(&format!("/api/v3/flows/instances/{}/", body.slug))
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let flow: Flow = res.json().await?;
+
+                Ok(flow)
+            }
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+// Only the fields which are set are sent, so anything else is left untouched.
+#[derive(Debug, Serialize, Default)]
+pub struct PatchFlowBody {
+    #[serde(skip_serializing)]
+    pub slug: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub designation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_engine_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compatibility_mode: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub denied_action: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum PatchFlowError {
+    #[error("The given flow was not found.")]
+    NotFound,
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::Group, AkApiRoute, AkClient};
+use crate::akapi::{types::Group, validation::ValidationErrors, AkApiRoute, AkClient};
 
 pub struct CreateGroup;
 
@@ -23,6 +23,15 @@ impl AkApiRoute for CreateGroup {
 
                 Ok(body)
             }
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
@@ -43,6 +52,8 @@ pub struct CreateGroupBody {
 pub enum CreateGroupError {
     #[error("The group probably already exists!")]
     ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
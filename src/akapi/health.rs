@@ -0,0 +1,34 @@
+use anyhow::{bail, Result};
+use tokio::time::{sleep, Duration, Instant};
+
+use super::client::HTTP_CLIENT;
+
+// How long to wait for the server to become ready, this has to fit in the timeout of a reconcile.
+static READY_TIMEOUT: Duration = Duration::from_secs(30);
+static POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// Wait until the server reports that it is ready, so the API calls that follow don't fail with
+// confusing connection errors while it is still starting.
+pub async fn wait_for_ready(instance: &str, ns: &str) -> Result<()> {
+    let url = format!("http://authentik-{}.{}/-/health/ready/", instance, ns);
+    let deadline = Instant::now() + READY_TIMEOUT;
+
+    loop {
+        // The shared client sends the extra headers, which a proxy in front of Authentik may require.
+        let failure = match HTTP_CLIENT.get(&url).timeout(POLL_INTERVAL).send().await {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            Ok(res) => format!("status code {}", res.status()),
+            Err(e) => e.to_string(),
+        };
+
+        if Instant::now() >= deadline {
+            bail!(
+                "Authentik did not become ready within {} seconds, the last check failed with {}.",
+                READY_TIMEOUT.as_secs(),
+                failure
+            );
+        }
+        debug!("Authentik is not ready yet ({}), checking again.", failure);
+        sleep(POLL_INTERVAL).await;
+    }
+}
@@ -1,10 +1,20 @@
+use std::time::Duration;
+
 use anyhow::Result;
 use async_trait::async_trait;
+use reqwest::{Response, StatusCode};
+use thiserror::Error;
+
+use validation::ValidationErrors;
 
+pub mod admin;
 pub mod application;
+pub mod blueprint;
+pub mod brand;
 pub mod certificate;
 pub mod flow;
 pub mod group;
+pub mod outpost;
 pub mod propertymappings;
 pub mod provider;
 pub mod stages;
@@ -12,19 +22,34 @@ pub mod token;
 pub mod user;
 
 pub mod auth;
+pub mod cache;
 mod client;
+pub mod ensure;
+pub mod exists;
+pub mod health;
+pub mod pagination;
+mod ratelimit;
 pub mod types;
+pub mod validation;
 
-pub use client::AkClient;
+pub use client::{retry_after, AkClient};
 
 pub static API_USER: &str = "ak-operator";
 
+// Response bodies are included in errors up to this length, error pages can be rather large.
+static MAX_ERROR_BODY_LENGTH: usize = 200;
+
 pub fn service_group_name(instance: &str) -> String {
     format!("akOperator {} service group", instance)
 }
 
+// Objects created by the operator are prefixed with this, so they can be found again during cleanup.
+pub fn managed_name_prefix(instance: &str) -> String {
+    format!("ak-operator-{}__", instance)
+}
+
 pub fn token_identifier_name(instance: &str, purpose: &str) -> String {
-    format!("ak-operator-{}__{}", instance, purpose)
+    format!("{}{}", managed_name_prefix(instance), purpose)
 }
 
 #[async_trait]
@@ -35,3 +60,38 @@ pub trait AkApiRoute {
 
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error>;
 }
+
+// Why a request was not successful, so routes handle the common status codes in the same way.
+#[derive(Error, Debug)]
+pub enum AkError {
+    #[error("The object was not found.")]
+    NotFound,
+    #[error("Server denied our authentication.")]
+    Forbidden,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("Authentik is rate limiting the operator (retry after {0:?}).")]
+    RateLimited(Option<Duration>),
+    #[error("Invalid status code {0} ({1}).")]
+    Status(StatusCode, String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
+
+// Pass successful responses on, and turn any others into an error with the details from the body.
+pub async fn classify_status(res: Response) -> Result<Response, AkError> {
+    match res.status() {
+        code if code.is_success() => Ok(res),
+        StatusCode::NOT_FOUND => Err(AkError::NotFound),
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(AkError::Forbidden),
+        StatusCode::TOO_MANY_REQUESTS => Err(AkError::RateLimited(retry_after(&res))),
+        StatusCode::BAD_REQUEST => Err(AkError::Validation(
+            ValidationErrors::from_response(res).await?,
+        )),
+        code => {
+            let body = res.text().await?;
+            let body: String = body.trim().chars().take(MAX_ERROR_BODY_LENGTH).collect();
+            Err(AkError::Status(code, body))
+        }
+    }
+}
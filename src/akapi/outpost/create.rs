@@ -0,0 +1,74 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::akapi::{
+    types::{Outpost, OutpostType},
+    validation::ValidationErrors,
+    AkApiRoute, AkClient,
+};
+
+pub struct CreateOutpost;
+
+#[async_trait]
+impl AkApiRoute for CreateOutpost {
+    type Body = CreateOutpostBody;
+    type Response = Outpost;
+    type Error = CreateOutpostError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .post("/api/v3/outposts/instances/")
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::CREATED => {
+                let body: Outpost = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateOutpostBody {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub outpost_type: OutpostType,
+    pub providers: Vec<usize>,
+    // Without a service connection the outpost has to be deployed externally.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_connection: Option<String>,
+    pub config: Value,
+}
+
+#[derive(Error, Debug)]
+pub enum CreateOutpostError {
+    #[error("The outpost already exists!")]
+    ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
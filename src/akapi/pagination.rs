@@ -0,0 +1,23 @@
+use serde::Deserialize;
+
+// A single page of a list response from Authentik.
+#[derive(Debug, Deserialize)]
+pub struct Paginated<T> {
+    pub pagination: Pagination,
+    pub results: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Pagination {
+    // The number of the next page, or 0 if this is the last page.
+    pub next: usize,
+}
+
+impl<T> Paginated<T> {
+    pub fn next_page(&self) -> Option<usize> {
+        match self.pagination.next {
+            0 => None,
+            next => Some(next),
+        }
+    }
+}
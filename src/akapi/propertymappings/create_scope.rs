@@ -0,0 +1,66 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::akapi::{types::ScopeMapping, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct CreateScopeMapping;
+
+#[async_trait]
+impl AkApiRoute for CreateScopeMapping {
+    type Body = CreateScopeMappingBody;
+    type Response = ScopeMapping;
+    type Error = CreateScopeMappingError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .post("/api/v3/propertymappings/provider/scope/")
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::CREATED => {
+                let body: ScopeMapping = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateScopeMappingBody {
+    pub name: String,
+    pub scope_name: String,
+    pub expression: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum CreateScopeMappingError {
+    #[error("The scope mapping already exists!")]
+    ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
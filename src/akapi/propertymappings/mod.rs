@@ -1,3 +1,7 @@
+mod create_scope;
+mod delete_scope;
 mod find_scope;
 
+pub use create_scope::*;
+pub use delete_scope::*;
 pub use find_scope::*;
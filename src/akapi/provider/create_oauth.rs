@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use reqwest::StatusCode;
 use thiserror::Error;
 
-use crate::akapi::{types::OAuthProvider, AkApiRoute, AkClient};
+use crate::akapi::{types::OAuthProvider, validation::ValidationErrors, AkApiRoute, AkClient};
 
 pub struct CreateOAuthProvider;
 
@@ -12,7 +12,7 @@ impl AkApiRoute for CreateOAuthProvider {
     type Response = OAuthProvider;
     type Error = CreateOAuthProviderError;
 
-    #[instrument]
+    #[instrument(skip(body))]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
         let res = ak
             .post("/api/v3/providers/oauth2/")
@@ -26,6 +26,9 @@ impl AkApiRoute for CreateOAuthProvider {
 
                 Ok(body)
             }
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
@@ -36,6 +39,8 @@ impl AkApiRoute for CreateOAuthProvider {
 
 #[derive(Error, Debug)]
 pub enum CreateOAuthProviderError {
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
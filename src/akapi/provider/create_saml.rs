@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{types::SamlProvider, validation::ValidationErrors, AkApiRoute, AkClient};
+
+pub struct CreateSamlProvider;
+
+#[async_trait]
+impl AkApiRoute for CreateSamlProvider {
+    type Body = SamlProvider;
+    type Response = SamlProvider;
+    type Error = CreateSamlProviderError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .post("/api/v3/providers/saml/")
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::CREATED => {
+                let body: SamlProvider = res.json().await?;
+
+                Ok(body)
+            }
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CreateSamlProviderError {
+    #[error("A provider with this name already exists!")]
+    ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
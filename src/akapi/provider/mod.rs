@@ -1,11 +1,15 @@
 mod create_oauth;
+mod create_saml;
 mod delete_oauth;
+mod delete_saml;
 mod find;
 mod find_oauth;
 mod patch_oauth;
 
 pub use create_oauth::*;
+pub use create_saml::*;
 pub use delete_oauth::*;
+pub use delete_saml::*;
 pub use find::*;
 pub use find_oauth::*;
 pub use patch_oauth::*;
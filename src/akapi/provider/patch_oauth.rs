@@ -12,7 +12,7 @@ impl AkApiRoute for PatchOAuthProvider {
     type Response = OAuthProvider;
     type Error = PatchOAuthProviderError;
 
-    #[instrument]
+    #[instrument(skip(body))]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
         let res = ak
             .patch(&format!("/api/v3/providers/oauth2/{}/", body.pk))
@@ -0,0 +1,70 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use lazy_static::lazy_static;
+
+lazy_static! {
+    // The limiters are shared by all clients for the same instance, so all reconciles count towards it.
+    static ref LIMITERS: Mutex<HashMap<String, Arc<RateLimiter>>> = Mutex::new(HashMap::new());
+    static ref RATE: f64 = std::env::var("AKAPI_RATE_LIMIT")
+        .ok()
+        .and_then(|rate| rate.parse().ok())
+        .unwrap_or(10.0);
+    static ref BURST: f64 = std::env::var("AKAPI_RATE_BURST")
+        .ok()
+        .and_then(|burst| burst.parse().ok())
+        .unwrap_or(20.0);
+}
+
+// A token bucket, which allows `burst` requests at once and refills at `rate` requests per second.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst,
+            state: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    // The limiter for the given instance, or none if rate limiting is disabled.
+    pub fn for_host(host: &str) -> Option<Arc<Self>> {
+        if *RATE <= 0.0 {
+            return None;
+        }
+
+        let mut limiters = LIMITERS.lock().expect("Failed to lock the rate limiters");
+        let limiter = limiters
+            .entry(host.to_string())
+            .or_insert_with(|| Arc::new(Self::new(*RATE, BURST.max(1.0))));
+        Some(limiter.clone())
+    }
+
+    // Wait until a request may be sent.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().expect("Failed to lock the rate limiter");
+                let (tokens, last) = *state;
+                let now = Instant::now();
+                let tokens =
+                    (tokens + now.duration_since(last).as_secs_f64() * self.rate).min(self.burst);
+                if tokens >= 1.0 {
+                    *state = (tokens - 1.0, now);
+                    return;
+                }
+                *state = (tokens, now);
+                Duration::from_secs_f64((1.0 - tokens) / self.rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
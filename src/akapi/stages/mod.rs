@@ -1,5 +1,7 @@
 mod delete;
+mod exists;
 mod find;
 
 pub use delete::*;
+pub use exists::*;
 pub use find::*;
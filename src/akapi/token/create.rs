@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::Token, AkApiRoute, AkClient};
+use crate::akapi::{types::Token, validation::ValidationErrors, AkApiRoute, AkClient};
 
 pub struct CreateToken;
 
@@ -23,7 +23,15 @@ impl AkApiRoute for CreateToken {
 
                 Ok(body)
             }
-            StatusCode::BAD_REQUEST => Err(Self::Error::ExistsError),
+            StatusCode::BAD_REQUEST => {
+                let errors = ValidationErrors::from_response(res).await?;
+
+                if errors.is_conflict() {
+                    Err(Self::Error::ExistsError)
+                } else {
+                    Err(Self::Error::Validation(errors))
+                }
+            }
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
@@ -45,6 +53,8 @@ pub struct CreateTokenBody {
 pub enum CreateTokenError {
     #[error("The token already exists!")]
     ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+use crate::akapi::{pagination::Paginated, types::Token, AkApiRoute, AkClient};
+
+pub struct FindToken;
+
+#[async_trait]
+impl AkApiRoute for FindToken {
+    type Body = ();
+    type Response = Vec<Token>;
+    type Error = FindTokenError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, _body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let mut tokens = Vec::new();
+        let mut page = Some(1);
+
+        while let Some(current) = page {
+            let query = vec![
+                ("page", current.to_string()),
+                ("page_size", "100".to_string()),
+            ];
+            let res = ak.get("/api/v3/core/tokens/").query(&query).send().await?;
+
+            match res.status() {
+                StatusCode::OK => {
+                    let body: Paginated<Token> = res.json().await?;
+
+                    page = body.next_page();
+                    tokens.extend(body.results);
+                }
+                code => {
+                    return Err(Self::Error::Unknown(format!(
+                        "Invalid status code {}",
+                        code
+                    )))
+                }
+            }
+        }
+
+        Ok(tokens)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum FindTokenError {
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
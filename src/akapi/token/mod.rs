@@ -1,7 +1,11 @@
 mod create;
 mod delete;
+mod find;
+mod set_key;
 mod view;
 
 pub use create::*;
 pub use delete::*;
+pub use find::*;
+pub use set_key::*;
 pub use view::*;
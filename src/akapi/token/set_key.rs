@@ -0,0 +1,50 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::akapi::{AkApiRoute, AkClient};
+
+pub struct SetTokenKey;
+
+#[async_trait]
+impl AkApiRoute for SetTokenKey {
+    type Body = SetTokenKeyBody;
+    type Response = ();
+    type Error = SetTokenKeyError;
+
+    #[instrument(skip(body))]
+    async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .post(&format!("/api/v3/core/tokens/{}/set_key/", body.identifier))
+            .json(&body)
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::NO_CONTENT => Ok(()),
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SetTokenKeyBody {
+    #[serde(skip_serializing)]
+    pub identifier: String,
+    pub key: String,
+}
+
+#[derive(Error, Debug)]
+pub enum SetTokenKeyError {
+    #[error("The given token was not found.")]
+    NotFound,
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
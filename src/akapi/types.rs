@@ -45,7 +45,7 @@ pub struct Stage {
     pub component: String,
 }
 
-#[derive(Debug, Deserialize, PartialEq)]
+#[derive(Debug, Deserialize, PartialEq, Clone)]
 pub struct Provider {
     pub pk: usize,
     pub name: String,
@@ -54,6 +54,20 @@ pub struct Provider {
     pub component: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Brand {
+    #[serde(skip_serializing, default)]
+    pub brand_uuid: String,
+    pub domain: String,
+    pub default: bool,
+    pub branding_title: String,
+    pub flow_authentication: Option<String>,
+    pub flow_invalidation: Option<String>,
+    pub flow_recovery: Option<String>,
+    pub flow_unenrollment: Option<String>,
+    pub flow_user_settings: Option<String>,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct OAuthProvider {
     #[serde(skip_serializing)]
@@ -73,6 +87,24 @@ pub struct OAuthProvider {
     pub issuer_mode: Option<IssuerMode>,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SamlProvider {
+    #[serde(skip_serializing)]
+    pub pk: usize,
+    pub name: String,
+    pub authorization_flow: String,
+    pub acs_url: String,
+    pub issuer: Option<String>,
+    pub sp_binding: Option<SamlBinding>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SamlBinding {
+    Redirect,
+    Post,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct Flow {
     pub pk: String,
@@ -82,6 +114,16 @@ pub struct Flow {
     pub background: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct FlowStageBinding {
+    pub pk: String,
+    pub target: String,
+    pub stage: String,
+    pub order: i32,
+    pub evaluate_on_plan: bool,
+    pub re_evaluate_policies: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ScopeMapping {
     pub pk: String,
@@ -100,7 +142,7 @@ pub struct Certificate {
     pub cert_expiry: String,
 }
 
-#[derive(Debug, Deserialize, Serialize, Default, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Default, PartialEq, Clone)]
 pub struct Application {
     #[serde(skip_serializing)]
     pub pk: String,
@@ -116,3 +158,35 @@ pub struct Application {
     pub policy_engine_mode: Option<PolicyMode>,
     pub group: String,
 }
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutpostType {
+    Proxy,
+    Ldap,
+    Radius,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Outpost {
+    pub pk: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub outpost_type: OutpostType,
+    pub providers: Vec<usize>,
+    pub service_connection: Option<String>,
+    pub config: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BlueprintInstance {
+    pub pk: String,
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub content: String,
+    pub enabled: bool,
+    // One of `successful`, `warning`, `error`, `orphaned` or `unknown`.
+    pub status: String,
+    pub last_applied: String,
+}
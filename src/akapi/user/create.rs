@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{types::User, AkApiRoute, AkClient};
+use crate::akapi::{types::User, validation::ValidationErrors, AkApiRoute, AkClient};
 
 pub struct CreateAccount;
 
@@ -23,6 +23,9 @@ impl AkApiRoute for CreateAccount {
 
                 Ok(body)
             }
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
@@ -42,6 +45,8 @@ pub struct CreateAccountBody {
 
 #[derive(Error, Debug)]
 pub enum CreateAccountError {
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
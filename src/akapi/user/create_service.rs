@@ -1,9 +1,8 @@
 use async_trait::async_trait;
-use reqwest::StatusCode;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::akapi::{AkApiRoute, AkClient};
+use crate::akapi::{classify_status, validation::ValidationErrors, AkApiRoute, AkClient, AkError};
 
 pub struct CreateServiceAccount;
 
@@ -21,17 +20,18 @@ impl AkApiRoute for CreateServiceAccount {
             .send()
             .await?;
 
-        match res.status() {
-            StatusCode::OK => {
+        match classify_status(res).await {
+            Ok(res) => {
                 let body: CreateServiceAccountResponse = res.json().await?;
 
                 Ok(body)
             }
-            StatusCode::BAD_REQUEST => Err(Self::Error::ExistsError),
-            code => Err(Self::Error::Unknown(format!(
-                "Invalid status code {}",
-                code
-            ))),
+            Err(AkError::Validation(errors)) if errors.is_conflict() => {
+                Err(Self::Error::ExistsError)
+            }
+            Err(AkError::Validation(errors)) => Err(Self::Error::Validation(errors)),
+            Err(AkError::ConnectionError(e)) => Err(Self::Error::ConnectionError(e)),
+            Err(e) => Err(Self::Error::Unknown(e.to_string())),
         }
     }
 }
@@ -54,6 +54,8 @@ pub struct CreateServiceAccountResponse {
 pub enum CreateServiceAccountError {
     #[error("The user probably already exists!")]
     ExistsError,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
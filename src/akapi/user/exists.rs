@@ -0,0 +1,20 @@
+use async_trait::async_trait;
+
+use crate::akapi::{
+    exists::{object_exists, ObjectExistsError},
+    AkApiRoute, AkClient,
+};
+
+pub struct UserExists;
+
+#[async_trait]
+impl AkApiRoute for UserExists {
+    type Body = usize;
+    type Response = bool;
+    type Error = ObjectExistsError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, pk: Self::Body) -> Result<Self::Response, Self::Error> {
+        object_exists(ak, &format!("/api/v3/core/users/{}/", pk)).await
+    }
+}
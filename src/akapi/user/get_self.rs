@@ -23,6 +23,7 @@ impl AkApiRoute for GetSelf {
 
                 Ok(body)
             }
+            StatusCode::UNAUTHORIZED => Err(Self::Error::Unauthorized),
             StatusCode::FORBIDDEN => Err(Self::Error::Forbidden),
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
@@ -40,6 +41,8 @@ pub struct GetSelfResponse {
 
 #[derive(Error, Debug)]
 pub enum GetSelfError {
+    #[error("Server rejected our API token.")]
+    Unauthorized,
     #[error("Server denied our authentication.")]
     Forbidden,
     #[error("An unknown error occured ({0}).")]
@@ -1,15 +1,19 @@
 mod create;
 mod create_service;
 mod delete_account;
+mod exists;
 mod find;
 mod get_self;
+mod recovery_link;
 mod set_password;
 mod update;
 
 pub use create::*;
 pub use create_service::*;
 pub use delete_account::*;
+pub use exists::*;
 pub use find::*;
 pub use get_self::*;
+pub use recovery_link::*;
 pub use set_password::*;
 pub use update::*;
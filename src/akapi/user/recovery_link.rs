@@ -0,0 +1,52 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::akapi::{AkApiRoute, AkClient};
+
+pub struct GetRecoveryLink;
+
+#[async_trait]
+impl AkApiRoute for GetRecoveryLink {
+    type Body = usize;
+    type Response = String;
+    type Error = GetRecoveryLinkError;
+
+    #[instrument]
+    async fn send(ak: &AkClient, id: Self::Body) -> Result<Self::Response, Self::Error> {
+        let res = ak
+            .get(&format!("/api/v3/core/users/{}/recovery/", id))
+            .send()
+            .await?;
+
+        match res.status() {
+            StatusCode::OK => {
+                let body: GetRecoveryLinkResponse = res.json().await?;
+
+                Ok(body.link)
+            }
+            // Authentik also responds with this if no recovery flow is configured.
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            code => Err(Self::Error::Unknown(format!(
+                "Invalid status code {}",
+                code
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GetRecoveryLinkResponse {
+    link: String,
+}
+
+#[derive(Error, Debug)]
+pub enum GetRecoveryLinkError {
+    #[error("The given user was not found, or no recovery flow is configured.")]
+    NotFound,
+    #[error("An unknown error occured ({0}).")]
+    Unknown(String),
+    #[error("Failed to send HTTP request: {0}")]
+    ConnectionError(#[from] reqwest::Error),
+}
@@ -3,7 +3,7 @@ use reqwest::StatusCode;
 use serde::Serialize;
 use thiserror::Error;
 
-use crate::akapi::{AkApiRoute, AkClient};
+use crate::akapi::{validation::ValidationErrors, AkApiRoute, AkClient};
 
 pub struct SetPassword;
 
@@ -13,7 +13,7 @@ impl AkApiRoute for SetPassword {
     type Response = ();
     type Error = SetPasswordError;
 
-    #[instrument]
+    #[instrument(skip(body))]
     async fn send(ak: &AkClient, body: Self::Body) -> Result<Self::Response, Self::Error> {
         let res = ak
             .post(&format!("/api/v3/core/users/{}/set_password/", body.id))
@@ -23,6 +23,10 @@ impl AkApiRoute for SetPassword {
 
         match res.status() {
             StatusCode::NO_CONTENT => Ok(()),
+            StatusCode::NOT_FOUND => Err(Self::Error::NotFound),
+            StatusCode::BAD_REQUEST => Err(Self::Error::Validation(
+                ValidationErrors::from_response(res).await?,
+            )),
             code => Err(Self::Error::Unknown(format!(
                 "Invalid status code {}",
                 code
@@ -39,6 +43,10 @@ pub struct SetPasswordBody {
 
 #[derive(Error, Debug)]
 pub enum SetPasswordError {
+    #[error("The given user was not found.")]
+    NotFound,
+    #[error("Authentik rejected the request ({0}).")]
+    Validation(ValidationErrors),
     #[error("An unknown error occured ({0}).")]
     Unknown(String),
     #[error("Failed to send HTTP request: {0}")]
@@ -0,0 +1,58 @@
+use std::{collections::BTreeMap, fmt};
+
+use reqwest::Response;
+use serde_json::Value;
+
+// The validation errors Authentik returns with a 400 response, by field name.
+#[derive(Debug, Default)]
+pub struct ValidationErrors(pub BTreeMap<String, Vec<String>>);
+
+impl ValidationErrors {
+    pub async fn from_response(res: Response) -> Result<Self, reqwest::Error> {
+        let body: Value = match res.json().await {
+            Ok(body) => body,
+            Err(e) if e.is_decode() => return Ok(Self::default()),
+            Err(e) => return Err(e),
+        };
+
+        let mut errors = BTreeMap::new();
+        if let Value::Object(fields) = body {
+            for (field, messages) in fields {
+                errors.insert(field, Self::messages(messages));
+            }
+        }
+
+        Ok(Self(errors))
+    }
+
+    // Whether the request was rejected because an object with the same unique values exists.
+    pub fn is_conflict(&self) -> bool {
+        self.0.values().flatten().any(|message| {
+            let message = message.to_lowercase();
+            message.contains("already exists") || message.contains("must be unique")
+        })
+    }
+
+    fn messages(value: Value) -> Vec<String> {
+        match value {
+            Value::String(message) => vec![message],
+            Value::Array(values) => values.into_iter().flat_map(Self::messages).collect(),
+            value => vec![value.to_string()],
+        }
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "no details given");
+        }
+
+        let fields: Vec<String> = self
+            .0
+            .iter()
+            .map(|(field, messages)| format!("{}: {}", field, messages.join(" ")))
+            .collect();
+        write!(f, "{}", fields.join(", "))
+    }
+}
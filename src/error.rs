@@ -3,7 +3,7 @@ use kube::runtime::finalizer;
 use thiserror::Error;
 
 #[derive(thiserror::Error, Debug)]
-#[error("Reconcile failed: {0}")]
+#[error("Reconcile failed: {0:#}")]
 pub struct ReconcileError(#[from] anyhow::Error);
 
 impl From<finalizer::Error<Self>> for ReconcileError {
@@ -18,12 +18,32 @@ impl From<finalizer::Error<Self>> for ReconcileError {
     }
 }
 
+#[derive(Error, Debug)]
+pub enum InvalidObj {
+    #[error("Invalid object, `{0}` is required when `{1}` is set.")]
+    MissingField(String, String),
+    #[error("Invalid object, `{0}` and `{1}` cannot have the same value.")]
+    Conflict(String, String),
+    #[error("Invalid object, `{0}` and `{1}` cannot both be set.")]
+    Exclusive(String, String),
+    #[error("Invalid object, `{0}` is invalid as {1}.")]
+    InvalidField(String, String),
+}
+
 #[derive(Error, Debug)]
 pub enum StartError {
+    #[error("Failed to create the Kubernetes client, check the kubeconfig or in-cluster service account: {0}")]
+    ClientError(#[source] kube::Error),
+    #[error("The operator is not allowed to list `{0}` resources, check its RBAC permissions.")]
+    Forbidden(String),
     #[error("Kube error: {0}")]
     KubeError(#[from] kube::Error),
     #[error("Failed to start webserver.")]
     IOError(#[from] std::io::Error),
+    #[error("Failed to serialize the CRDs: {0}")]
+    SerializeError(#[from] serde_yaml::Error),
+    #[error("Failed to reconcile {0} of the Authentik objects.")]
+    ReconcileFailed(usize),
     #[error("Failed to initialize tracing logger")]
     TracingError,
 }
@@ -4,8 +4,8 @@ extern crate tracing;
 use actix_web::{get, middleware, App, HttpRequest, HttpResponse, HttpServer, Responder};
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
 use kube::{
-    api::{Patch, PatchParams},
-    Api, Client, CustomResourceExt, ResourceExt,
+    api::{ListParams, Patch, PatchParams},
+    Api, Client, ResourceExt,
 };
 use tracing_subscriber::{prelude::*, EnvFilter, Registry};
 
@@ -19,6 +19,12 @@ async fn health(_: HttpRequest) -> impl Responder {
 
 #[tokio::main]
 async fn main() -> Result<(), StartError> {
+    // Print the CRDs instead of running the operator, so they can be installed separately.
+    if std::env::args().skip(1).any(|arg| arg == "--print-crd") {
+        print!("{}", resources::crds_yaml()?);
+        return Ok(());
+    }
+
     let logger = tracing_subscriber::fmt::layer();
     let env_filter = EnvFilter::try_from_default_env()
         .or_else(|_| EnvFilter::try_new("info"))
@@ -27,6 +33,37 @@ async fn main() -> Result<(), StartError> {
     let collector = Registry::default().with(logger).with(env_filter);
     tracing::subscriber::set_global_default(collector).map_err(|_| StartError::TracingError)?;
 
+    if let Err(e) = run().await {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+async fn run() -> Result<(), StartError> {
+    let client = Client::try_default()
+        .await
+        .map_err(StartError::ClientError)?;
+
+    match resources::watch_namespace() {
+        Some(ns) => info!("Watching resources in namespace `{}`.", ns),
+        None => info!("Watching resources in all namespaces."),
+    }
+    if let Some(selector) = resources::watch_selector() {
+        info!(
+            "Only managing objects matching the selector `{}`.",
+            selector
+        );
+    }
+
+    ensure_crds(client.clone()).await?;
+    check_permissions(client.clone()).await?;
+
+    if reconcile_once() {
+        return run_once(client).await;
+    }
+
     let server = HttpServer::new(move || {
         App::new()
             .wrap(middleware::Logger::default().exclude("/health"))
@@ -35,28 +72,36 @@ async fn main() -> Result<(), StartError> {
     .bind("0.0.0.0:8080")?
     .shutdown_timeout(5);
 
-    ensure_crds().await?;
-
     tokio::select! {
-        _ = start_managers() => warn!("A manager exited"),
+        _ = start_managers(client) => warn!("A manager exited"),
         _ = server.run() => warn!("Actix Web exited"),
     }
     Ok(())
 }
 
-async fn ensure_crds() -> Result<(), StartError> {
-    let crds = [
-        resources::authentik::crd::Authentik::crd(),
-        resources::authentik_application::crd::AuthentikApplication::crd(),
-        resources::authentik_group::crd::AuthentikGroup::crd(),
-        resources::authentik_user::crd::AuthentikUser::crd(),
-        resources::authentik_provider_oauth::crd::AuthentikOAuthProvider::crd(),
-    ];
+// Reconcile all objects once and exit, for example to validate manifests in CI.
+fn reconcile_once() -> bool {
+    std::env::args().skip(1).any(|arg| arg == "--once")
+        || std::env::var("RECONCILE_ONCE").is_ok_and(|once| once == "true" || once == "1")
+}
+
+async fn run_once(client: Client) -> Result<(), StartError> {
+    info!("Reconciling all Authentik objects once.");
+    let failed = resources::AuthentikManager::reconcile_once(client).await?;
+
+    if failed.is_empty() {
+        info!("All Authentik objects were reconciled successfully.");
+        return Ok(());
+    }
+
+    error!("Failed to reconcile Authentik: {}", failed.join(", "));
+    Err(StartError::ReconcileFailed(failed.len()))
+}
 
-    let client = Client::try_default().await?;
+async fn ensure_crds(client: Client) -> Result<(), StartError> {
     let api: Api<CustomResourceDefinition> = Api::all(client);
 
-    for crd in crds {
+    for crd in resources::crds() {
         api.patch(
             &crd.name_any(),
             &PatchParams::apply("ak-operator").force(),
@@ -68,12 +113,25 @@ async fn ensure_crds() -> Result<(), StartError> {
     Ok(())
 }
 
-async fn start_managers() -> Result<(), StartError> {
-    let authentik_mgr = resources::AuthentikManager::new(Client::try_default().await?);
-    let authentik_app_mgr = resources::AuthentikAppManager::new(Client::try_default().await?);
-    let authentik_user_mgr = resources::AuthentikUserManager::new(Client::try_default().await?);
-    let authentik_group_mgr = resources::AuthentikGroupManager::new(Client::try_default().await?);
-    let authentik_oauth_mgr = resources::AuthentikOAuthManager::new(Client::try_default().await?);
+async fn check_permissions(client: Client) -> Result<(), StartError> {
+    // List the Authentik resources once, so missing permissions are reported at startup.
+    let api: Api<resources::authentik::crd::Authentik> = resources::watched_api(client);
+
+    match api.list(&ListParams::default().limit(1)).await {
+        Ok(_) => Ok(()),
+        Err(kube::Error::Api(e)) if e.code == 403 => {
+            Err(StartError::Forbidden("Authentik".to_string()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+async fn start_managers(client: Client) -> Result<(), StartError> {
+    let authentik_mgr = resources::AuthentikManager::new(client.clone());
+    let authentik_app_mgr = resources::AuthentikAppManager::new(client.clone());
+    let authentik_user_mgr = resources::AuthentikUserManager::new(client.clone());
+    let authentik_group_mgr = resources::AuthentikGroupManager::new(client.clone());
+    let authentik_oauth_mgr = resources::AuthentikOAuthManager::new(client);
 
     tokio::select! {
         _ = authentik_mgr => warn!("Authentik controller exited"),
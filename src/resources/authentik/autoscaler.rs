@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::autoscaling::v2::HorizontalPodAutoscaler;
+use kube::{api::DeleteParams, Api, Client, ResourceExt};
+use serde_json::{json, Value};
+
+use crate::resources::apply_if_changed;
+
+use super::{crd, labels};
+
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let name = format!("authentik-{}-worker", instance);
+    let api: Api<HorizontalPodAutoscaler> = Api::namespaced(client, &ns);
+    let autoscaler = api.get_opt(&name).await?;
+
+    match &obj.spec.worker.autoscaling {
+        // Create or update the autoscaler.
+        Some(autoscaling) if !obj.spec.suspended => {
+            apply_if_changed(&api, &name, build(instance.clone(), obj, autoscaling)).await?;
+        }
+        // Remove the autoscaler, as it's no longer in the CRD defined or would scale the suspended
+        // worker back up.
+        _ if autoscaler.is_some() => {
+            api.delete(&name, &DeleteParams::default()).await?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
+    Ok(())
+}
+
+fn build(name: String, obj: &crd::Authentik, autoscaling: &crd::AuthentikAutoscaling) -> Value {
+    let mut metrics = Vec::new();
+    if let Some(cpu) = autoscaling.target_cpu_utilization {
+        metrics.push(build_metric("cpu", cpu));
+    }
+    if let Some(memory) = autoscaling.target_memory_utilization {
+        metrics.push(build_metric("memory", memory));
+    }
+    if metrics.is_empty() {
+        // Scale on CPU by default, an autoscaler without metrics does nothing.
+        metrics.push(build_metric("cpu", 80));
+    }
+
+    json!({
+        "apiVersion": "autoscaling/v2",
+        "kind": "HorizontalPodAutoscaler",
+        "metadata": {
+            "name": format!("authentik-{}-worker", name.clone()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "autoscaler".to_string()),
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "spec": {
+            "scaleTargetRef": {
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "name": format!("authentik-{}-worker", name.clone()),
+            },
+            "minReplicas": autoscaling.min_replicas,
+            "maxReplicas": autoscaling.max_replicas,
+            "metrics": metrics,
+            "behavior": {
+                "scaleDown": {
+                    "stabilizationWindowSeconds": autoscaling.scale_down.stabilization_window_seconds,
+                    "selectPolicy": autoscaling.scale_down.select_policy,
+                    "policies": autoscaling.scale_down.policies.iter().map(|policy| json!({
+                        "type": policy.policy_type,
+                        "value": policy.value,
+                        "periodSeconds": policy.period_seconds,
+                    })).collect::<Vec<Value>>()
+                }
+            }
+        }
+    })
+}
+
+fn build_metric(resource: &str, utilization: u32) -> Value {
+    json!({
+        "type": "Resource",
+        "resource": {
+            "name": resource,
+            "target": {
+                "type": "Utilization",
+                "averageUtilization": utilization
+            }
+        }
+    })
+}
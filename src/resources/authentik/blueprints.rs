@@ -0,0 +1,201 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::{Api, Client, ResourceExt};
+use lazy_static::lazy_static;
+use serde_json::json;
+
+use crate::akapi::{
+    auth::get_valid_token,
+    blueprint::{
+        ApplyBlueprint, CreateBlueprint, CreateBlueprintBody, FindBlueprint, PatchBlueprint,
+        PatchBlueprintBody,
+    },
+    managed_name_prefix, AkApiRoute, AkClient,
+};
+
+use super::crd;
+
+// Fetching a blueprint from a URL may not hold up the reconcile for longer than this.
+static FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+// The largest blueprint that is fetched from a URL, anything bigger is refused.
+static MAX_FETCH_SIZE: usize = 1024 * 1024;
+// How long a fetched blueprint is reused, so not every reconcile requests the URL again.
+static FETCH_CACHE_TIME: Duration = Duration::from_secs(5 * 60);
+
+lazy_static! {
+    // This is separate from the client of the API, as that sends the extra headers meant for
+    // Authentik, which must not reach arbitrary hosts.
+    static ref FETCH_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .user_agent(format!("{}/{}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")))
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .expect("Failed to build the HTTP client for blueprints");
+    static ref FETCHED: Mutex<HashMap<String, (Instant, String)>> = Mutex::new(HashMap::new());
+}
+
+// Store the blueprints in Authentik and apply these whenever their content changes.
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let prefix = managed_name_prefix(&instance);
+
+    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
+    let ak = AkClient::new(&api_key, &instance, &ns)?;
+
+    let instances = FindBlueprint::send(&ak, ()).await?;
+
+    for blueprint in obj.spec.blueprints.iter() {
+        let name = format!("{}{}", prefix, blueprint.name);
+        let content = load(blueprint, client.clone(), &ns)
+            .await
+            .with_context(|| format!("Failed to load blueprint `{}`.", blueprint.name))?;
+        if let Err(e) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+            bail!("Blueprint `{}` is not valid YAML: {}", blueprint.name, e);
+        }
+
+        let pk = match instances.iter().find(|existing| existing.name == name) {
+            // Failed blueprints are applied again, the error could have been caused by other objects.
+            Some(existing)
+                if existing.content == content
+                    && existing.enabled
+                    && existing.status != "error" =>
+            {
+                continue
+            }
+            Some(existing) => {
+                PatchBlueprint::send(
+                    &ak,
+                    PatchBlueprintBody {
+                        pk: existing.pk.clone(),
+                        content: Some(content),
+                        enabled: Some(true),
+                    },
+                )
+                .await?
+                .pk
+            }
+            None => {
+                CreateBlueprint::send(
+                    &ak,
+                    CreateBlueprintBody {
+                        name,
+                        path: "".to_string(),
+                        content,
+                        context: json!({}),
+                        enabled: true,
+                    },
+                )
+                .await?
+                .pk
+            }
+        };
+
+        let applied = ApplyBlueprint::send(&ak, pk).await?;
+        if applied.status == "error" {
+            bail!("Authentik failed to apply blueprint `{}`.", blueprint.name);
+        }
+        info!("Applied blueprint `{}`.", blueprint.name);
+    }
+
+    // Blueprints that were removed from the spec are disabled, so the worker stops applying these.
+    // The objects these created are left alone.
+    for existing in instances.iter().filter(|existing| {
+        existing.enabled
+            && existing.name.starts_with(&prefix)
+            && !obj
+                .spec
+                .blueprints
+                .iter()
+                .any(|blueprint| existing.name[prefix.len()..] == blueprint.name)
+    }) {
+        PatchBlueprint::send(
+            &ak,
+            PatchBlueprintBody {
+                pk: existing.pk.clone(),
+                content: None,
+                enabled: Some(false),
+            },
+        )
+        .await?;
+        info!("Disabled removed blueprint `{}`.", existing.name);
+    }
+
+    Ok(())
+}
+
+async fn load(blueprint: &crd::AuthentikBlueprint, client: Client, ns: &str) -> Result<String> {
+    if let Some(content) = blueprint.content.as_ref() {
+        return Ok(content.clone());
+    }
+
+    if let Some(reference) = blueprint.config_map_ref.as_ref() {
+        let api: Api<ConfigMap> = Api::namespaced(client, ns);
+        let config_map = api.get_opt(&reference.name).await?.ok_or(anyhow!(
+            "Referenced ConfigMap `{}` does not exist.",
+            reference.name
+        ))?;
+        return config_map
+            .data
+            .and_then(|mut data| data.remove(&reference.key))
+            .ok_or(anyhow!(
+                "Referenced ConfigMap `{}` has no key `{}`.",
+                reference.name,
+                reference.key
+            ));
+    }
+
+    if let Some(url) = blueprint.url.as_ref() {
+        return fetch(url).await;
+    }
+
+    Err(anyhow!("Missing the source of the blueprint."))
+}
+
+async fn fetch(url: &str) -> Result<String> {
+    if let Some((at, content)) = FETCHED
+        .lock()
+        .expect("Failed to lock the fetched blueprints")
+        .get(url)
+    {
+        if at.elapsed() < FETCH_CACHE_TIME {
+            return Ok(content.clone());
+        }
+    }
+
+    let mut res = FETCH_CLIENT.get(url).send().await?.error_for_status()?;
+    let too_large = || anyhow!("The blueprint is larger than {} bytes.", MAX_FETCH_SIZE);
+    if res
+        .content_length()
+        .is_some_and(|length| length > MAX_FETCH_SIZE as u64)
+    {
+        return Err(too_large());
+    }
+
+    // The length isn't always known up front, so the limit is also checked while reading.
+    let mut body = Vec::new();
+    while let Some(chunk) = res.chunk().await? {
+        if body.len() + chunk.len() > MAX_FETCH_SIZE {
+            return Err(too_large());
+        }
+        body.extend_from_slice(&chunk);
+    }
+    let content = String::from_utf8(body).context("The blueprint is not valid UTF-8.")?;
+
+    FETCHED
+        .lock()
+        .expect("Failed to lock the fetched blueprints")
+        .insert(url.to_string(), (Instant::now(), content.clone()));
+    Ok(content)
+}
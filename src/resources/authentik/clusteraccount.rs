@@ -3,12 +3,11 @@ use k8s_openapi::api::{
     core::v1::ServiceAccount,
     rbac::v1::{ClusterRole, ClusterRoleBinding},
 };
-use kube::{
-    api::{DeleteParams, Patch, PatchParams},
-    Api, Client, ResourceExt,
-};
+use kube::{api::DeleteParams, Api, Client, ResourceExt};
 use serde_json::{json, Value};
 
+use crate::resources::apply_if_changed;
+
 use super::{crd, labels};
 
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
@@ -21,30 +20,33 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
-    // Create the service account.
+    // Create the service account, unless an existing one is used.
+    let name = format!("ak-{}", &instance);
     let api: Api<ServiceAccount> = Api::namespaced(client.clone(), &ns);
-    api.patch(
-        &format!("ak-{}", &instance),
-        &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_serviceaccount(instance.clone(), obj)),
-    )
-    .await?;
+    if obj.spec.service_account_name.is_none() {
+        apply_if_changed(&api, &name, build_serviceaccount(instance.clone(), obj)).await?;
+    } else if obj.spec.service_account_name(&instance) != name
+        && api.get_opt(&name).await?.is_some()
+    {
+        // Remove the service account created before an existing one was given.
+        api.delete(&name, &DeleteParams::default()).await?;
+    }
 
     // Create the cluster role.
     let api: Api<ClusterRole> = Api::all(client.clone());
-    api.patch(
+    apply_if_changed(
+        &api,
         &format!("ak-{}", &instance),
-        &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_clusterrole(instance.clone(), obj)),
+        build_clusterrole(instance.clone(), obj),
     )
     .await?;
 
     // Create the cluster role binding.
     let api: Api<ClusterRoleBinding> = Api::all(client.clone());
-    api.patch(
+    apply_if_changed(
+        &api,
         &format!("ak-{}", &instance),
-        &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_binding(instance.clone(), obj, &ns)),
+        build_binding(instance.clone(), obj, &ns),
     )
     .await?;
 
@@ -76,7 +78,7 @@ fn build_serviceaccount(name: String, obj: &crd::Authentik) -> Value {
         "kind": "ServiceAccount",
         "metadata": {
             "name": format!("ak-{}", &name),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "clusteraccount".to_string()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "clusteraccount".to_string()),
             "ownerReferences": [{
                 "apiVersion": "ak.dany.dev/v1",
                 "kind": "Authentik",
@@ -94,7 +96,7 @@ fn build_clusterrole(name: String, obj: &crd::Authentik) -> Value {
         "kind": "ClusterRole",
         "metadata": {
             "name": format!("ak-{}", &name),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "clusteraccount".to_string())
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "clusteraccount".to_string())
         },
         "rules": [
             {
@@ -137,7 +139,7 @@ fn build_binding(name: String, obj: &crd::Authentik, ns: &str) -> Value {
         "kind": "ClusterRoleBinding",
         "metadata": {
             "name": format!("ak-{}", &name),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "clusteraccount".to_string())
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "clusteraccount".to_string())
         },
         "roleRef": {
             "apiGroup": "rbac.authorization.k8s.io",
@@ -146,7 +148,7 @@ fn build_binding(name: String, obj: &crd::Authentik, ns: &str) -> Value {
         },
         "subjects": [{
             "kind": "ServiceAccount",
-            "name": format!("ak-{}", &name),
+            "name": obj.spec.service_account_name(&name),
             "namespace": ns
         }]
     })
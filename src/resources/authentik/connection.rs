@@ -0,0 +1,278 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::core::v1::{ConfigMap, EnvVar, EnvVarSource, Secret, SecretKeySelector};
+use kube::{Api, Client};
+
+use crate::{resources::hash_value, InvalidObj};
+
+use super::crd;
+
+static DEFAULT_POSTGRES_PORT: u16 = 5432;
+static DEFAULT_POSTGRES_PASSWORD: &str = "postgres";
+static DEFAULT_REDIS_PORT: u16 = 6379;
+// Django warns about secret keys shorter than this, as these are easier to guess.
+static MIN_SECRET_KEY_LENGTH: usize = 50;
+// The hash of the secret key is visible to anyone who can read the pods, so it is salted.
+static SECRET_KEY_HASH_SALT: &str = "ak.dany.dev/secret-key";
+
+// A value that is either given directly, or read from a key in a secret by the pod.
+pub enum Value {
+    Plain(String),
+    SecretKey(String, String),
+}
+
+impl Value {
+    pub fn to_env(&self, name: &str) -> EnvVar {
+        match self {
+            Value::Plain(value) => EnvVar {
+                name: name.to_string(),
+                value: Some(value.clone()),
+                value_from: None,
+            },
+            Value::SecretKey(secret, key) => EnvVar {
+                name: name.to_string(),
+                value: None,
+                value_from: Some(EnvVarSource {
+                    config_map_key_ref: None,
+                    field_ref: None,
+                    resource_field_ref: None,
+                    secret_key_ref: Some(SecretKeySelector {
+                        key: key.clone(),
+                        name: Some(secret.clone()),
+                        optional: Some(false),
+                    }),
+                }),
+            },
+        }
+    }
+}
+
+pub struct Postgres {
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    pub username: Value,
+    pub password: Value,
+}
+
+pub enum RedisEndpoint {
+    Standalone(String, u16),
+    Sentinel(crd::AuthentikRedisSentinel),
+}
+
+pub struct Redis {
+    pub endpoint: RedisEndpoint,
+    pub password: Option<Value>,
+}
+
+pub struct SecretKey {
+    pub value: Value,
+    pub hash: String,
+}
+
+pub struct Connection {
+    pub postgres: Postgres,
+    pub redis: Redis,
+    pub secret_key: SecretKey,
+}
+
+// Resolve the postgres and redis connection details, and the secret key.
+// Inline values take precedence over the values in the referenced ConfigMap and Secret.
+pub async fn resolve(obj: &crd::AuthentikSpec, client: Client, ns: &str) -> Result<Connection> {
+    Ok(Connection {
+        postgres: resolve_postgres(&obj.postgres, client.clone(), ns).await?,
+        redis: resolve_redis(&obj.redis, client.clone(), ns).await?,
+        secret_key: resolve_secret_key(obj, client, ns).await?,
+    })
+}
+
+// Unlike the other secrets the secret key itself is read, to check it and to restart the pods when
+// it changes. Only its salted hash is kept.
+async fn resolve_secret_key(
+    obj: &crd::AuthentikSpec,
+    client: Client,
+    ns: &str,
+) -> Result<SecretKey> {
+    let (value, key) = match obj.secret_key_secret_ref.as_ref() {
+        Some(secret_ref) => {
+            let api: Api<Secret> = Api::namespaced(client, ns);
+            let secret = api.get_opt(&secret_ref.name).await?.ok_or(anyhow!(
+                "Referenced Secret `{}` does not exist.",
+                secret_ref.name
+            ))?;
+            let key = secret
+                .data
+                .unwrap_or_default()
+                .remove(&secret_ref.key)
+                .ok_or(anyhow!(
+                    "Referenced Secret `{}` does not contain the `{}` key.",
+                    secret_ref.name,
+                    secret_ref.key
+                ))?;
+
+            (
+                Value::SecretKey(secret_ref.name.clone(), secret_ref.key.clone()),
+                String::from_utf8(key.0)?,
+            )
+        }
+        None => {
+            let key = obj
+                .secret_key
+                .clone()
+                .ok_or(anyhow!("Missing `secretKey`."))?;
+            (Value::Plain(key.clone()), key)
+        }
+    };
+
+    if key.chars().count() < MIN_SECRET_KEY_LENGTH {
+        let field = match obj.secret_key_secret_ref {
+            Some(_) => "secretKeySecretRef",
+            None => "secretKey",
+        };
+        return Err(InvalidObj::InvalidField(
+            field.to_string(),
+            format!(
+                "the secret key must be at least {} characters long",
+                MIN_SECRET_KEY_LENGTH
+            ),
+        )
+        .into());
+    }
+
+    Ok(SecretKey {
+        value,
+        hash: hash_value(&serde_json::json!([SECRET_KEY_HASH_SALT, ns, key])),
+    })
+}
+
+async fn resolve_postgres(
+    obj: &crd::AuthentikPostgres,
+    client: Client,
+    ns: &str,
+) -> Result<Postgres> {
+    let config = read_config_map(obj.config_map_ref.as_ref(), client.clone(), ns).await?;
+    let secret = read_secret_keys(obj.secret_ref.as_ref(), client, ns).await?;
+
+    let host = config_value(obj.host.clone(), &config, "postgres", "host")?;
+    let database = config_value(obj.database.clone(), &config, "postgres", "database")?;
+    let port = match obj.port {
+        Some(port) => port,
+        None => config_port(&config, "postgres")?.unwrap_or(DEFAULT_POSTGRES_PORT),
+    };
+
+    let username = match obj.username.clone() {
+        Some(username) => Value::Plain(username),
+        None => secret_value(obj.secret_ref.as_ref(), &secret, "username").ok_or(anyhow!(
+            "Missing `postgres.username`, set it inline or in the `username` key of the referenced Secret."
+        ))?,
+    };
+
+    let password = if let Some((secret, key)) = obj
+        .password_secret
+        .clone()
+        .zip(obj.password_secret_key.clone())
+    {
+        Value::SecretKey(secret, key)
+    } else if let Some(password) = obj.password.clone() {
+        Value::Plain(password)
+    } else {
+        secret_value(obj.secret_ref.as_ref(), &secret, "password")
+            .unwrap_or(Value::Plain(DEFAULT_POSTGRES_PASSWORD.to_string()))
+    };
+
+    Ok(Postgres {
+        host,
+        port,
+        database,
+        username,
+        password,
+    })
+}
+
+async fn resolve_redis(obj: &crd::AuthentikRedis, client: Client, ns: &str) -> Result<Redis> {
+    let config = read_config_map(obj.config_map_ref.as_ref(), client.clone(), ns).await?;
+    let secret = read_secret_keys(obj.secret_ref.as_ref(), client, ns).await?;
+
+    let endpoint = match obj.sentinel.clone() {
+        Some(sentinel) => RedisEndpoint::Sentinel(sentinel),
+        None => RedisEndpoint::Standalone(
+            config_value(obj.host.clone(), &config, "redis", "host")?,
+            match obj.port {
+                Some(port) => port,
+                None => config_port(&config, "redis")?.unwrap_or(DEFAULT_REDIS_PORT),
+            },
+        ),
+    };
+
+    let password = match obj.password.clone() {
+        Some(password) => Some(Value::Plain(password)),
+        None => secret_value(obj.secret_ref.as_ref(), &secret, "password"),
+    };
+
+    Ok(Redis { endpoint, password })
+}
+
+async fn read_config_map(
+    name: Option<&String>,
+    client: Client,
+    ns: &str,
+) -> Result<BTreeMap<String, String>> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(BTreeMap::new()),
+    };
+
+    let api: Api<ConfigMap> = Api::namespaced(client, ns);
+    let config_map = api
+        .get_opt(name)
+        .await?
+        .ok_or(anyhow!("Referenced ConfigMap `{}` does not exist.", name))?;
+
+    Ok(config_map.data.unwrap_or_default())
+}
+
+// Only the keys are read, the values are passed to the pods by reference.
+async fn read_secret_keys(name: Option<&String>, client: Client, ns: &str) -> Result<Vec<String>> {
+    let name = match name {
+        Some(name) => name,
+        None => return Ok(vec![]),
+    };
+
+    let api: Api<Secret> = Api::namespaced(client, ns);
+    let secret = api
+        .get_opt(name)
+        .await?
+        .ok_or(anyhow!("Referenced Secret `{}` does not exist.", name))?;
+
+    Ok(secret.data.unwrap_or_default().into_keys().collect())
+}
+
+fn config_value(
+    inline: Option<String>,
+    config: &BTreeMap<String, String>,
+    section: &str,
+    key: &str,
+) -> Result<String> {
+    inline.or_else(|| config.get(key).cloned()).ok_or(anyhow!(
+        "Missing `{}.{}`, set it inline or in the `{}` key of the referenced ConfigMap.",
+        section,
+        key,
+        key
+    ))
+}
+
+fn config_port(config: &BTreeMap<String, String>, section: &str) -> Result<Option<u16>> {
+    config
+        .get("port")
+        .map(|port| {
+            port.parse()
+                .map_err(|_| anyhow!("Invalid `{}.port` in ConfigMap: `{}`.", section, port))
+        })
+        .transpose()
+}
+
+fn secret_value(name: Option<&String>, keys: &[String], key: &str) -> Option<Value> {
+    name.filter(|_| keys.iter().any(|k| k == key))
+        .map(|name| Value::SecretKey(name.clone(), key.to_string()))
+}
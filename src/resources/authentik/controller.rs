@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use kube::{
     api::{Api, Patch, PatchParams, ResourceExt},
     runtime::controller::Action,
@@ -10,21 +10,53 @@ use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde_json::{json, Map};
 use tokio::time::Duration;
 
+use crate::{
+    akapi::{auth::check_secret_token, cache::ListCache, health::wait_for_ready},
+    resources::{failed_part, publish_warning, requeue_interval},
+};
+
 use super::{
-    clusteraccount, crd, deployment, ingress, secret, service, serviceaccount, servicegroup,
+    autoscaler, blueprints, clusteraccount, crd, deployment, diagnostics, drift::Drift, ingress,
+    inventory, managedobjects, migration, orphan, secret, service, serviceaccount, servicegroup,
+    smtp, status, tokens,
 };
 
 pub struct Controller {
     client: Client,
+    drift: Arc<Drift>,
 }
 
 impl Controller {
     pub fn new(client: Client) -> Self {
-        Self { client }
+        Self {
+            client,
+            drift: Arc::new(Drift::default()),
+        }
+    }
+
+    pub fn drift(&self) -> Arc<Drift> {
+        self.drift.clone()
     }
 
     pub async fn reconcile(&self, obj: Arc<crd::Authentik>) -> Result<Action> {
+        // Most reconciles are triggered by the status updates of the previous one, these can be
+        // skipped when nothing changed since the last full reconcile.
+        if let Some(remaining) = self.drift.unchanged_for(&obj) {
+            debug!(
+                "Authentik is unchanged since the last reconcilidation, re-queued for {} seconds.",
+                remaining.as_secs()
+            );
+            return Ok(Action::requeue(remaining));
+        }
+        self.drift.start(&obj);
+
         info!("Starting reconcilidation of Authentik.");
+        if let Some(requested) = status::forced_reconcile(&obj) {
+            info!(
+                "Reconcilidation of Authentik was forced with the annotation (`{}`).",
+                requested
+            );
+        }
         let name = obj.name_any();
         let ns = obj
             .namespace()
@@ -40,32 +72,222 @@ impl Controller {
             return Ok(Action::requeue(Duration::from_secs(1)));
         }
 
-        // Reconcile all parts.
-        clusteraccount::reconcile(&obj, self.client.clone()).await?;
-        deployment::reconcile(&obj, self.client.clone()).await?;
-        service::reconcile(&obj, self.client.clone()).await?;
-        ingress::reconcile(&obj, self.client.clone()).await?;
-        serviceaccount::reconcile(&obj, self.client.clone()).await?;
-        servicegroup::reconcile(&obj, self.client.clone()).await?;
-        secret::reconcile(&obj, self.client.clone()).await?;
-
-        info!("Reconcilidation of Authentik finished successfully, re-queued for 30 minutes.");
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
+        // Reconcile all parts, and keep track of the outcome in the status.
+        let result = match self.reconcile_parts(&obj).await {
+            Ok(Some(readiness)) => Ok(readiness),
+            Ok(None) if obj.spec.suspended => Ok(status::Readiness::Suspended),
+            Ok(None) => migration::check(&obj, self.client.clone()).await,
+            Err(e) => Err(e),
+        };
+        let smtp = smtp::condition(&obj, self.client.clone())
+            .await
+            .unwrap_or_else(|e| {
+                warn!(
+                    "Failed to get the outcome of the SMTP connection test: {}",
+                    e
+                );
+                None
+            });
+        if let Err(e) = status::record(&obj, &servers, &result, smtp).await {
+            warn!("Failed to record the reconcile result of Authentik: {}", e);
+        }
+
+        if let status::Readiness::Migrating(message) = result? {
+            info!(
+                "Authentik is not ready yet, re-queued for 10 seconds. ({})",
+                message
+            );
+            return Ok(Action::requeue(Duration::from_secs(10)));
+        }
+
+        self.drift.finish(&obj);
+        let interval = requeue_interval(&obj);
+        info!(
+            "Reconcilidation of Authentik finished successfully, re-queued for {} seconds.",
+            interval.as_secs()
+        );
+        Ok(Action::requeue(interval))
     }
 
     pub async fn cleanup(&self, obj: Arc<crd::Authentik>) -> Result<Action> {
-        // Cleanup all parts.
-        secret::cleanup(obj.as_ref(), self.client.clone()).await?;
-        servicegroup::cleanup(obj.as_ref(), self.client.clone()).await?;
-        serviceaccount::cleanup(obj.as_ref(), self.client.clone()).await?;
-        ingress::cleanup(obj.as_ref(), self.client.clone()).await?;
-        service::cleanup(obj.as_ref(), self.client.clone()).await?;
-        deployment::cleanup(obj.as_ref(), self.client.clone()).await?;
-        clusteraccount::cleanup(obj.as_ref(), self.client.clone()).await?;
+        // Keep everything running, so a new instance with the same name can adopt it again.
+        if obj.spec.deletion_policy == crd::DeletionPolicy::Orphan {
+            orphan::release(obj.as_ref(), self.client.clone())
+                .await
+                .with_context(|| failed_part("cleaning up", "owner references", obj.as_ref()))?;
+            return Ok(Action::await_change());
+        }
+
+        // Cleanup all parts, starting with the objects in Authentik while the API is still usable.
+        // Authentik isn't running while suspended, so these are left behind instead of blocking the
+        // deletion forever.
+        if obj.spec.suspended {
+            let note = "The instance is suspended, so the objects the operator created in Authentik are left behind.".to_string();
+            warn!("{}", note);
+            publish_warning(self.client.clone(), obj.as_ref(), "CleanupSkipped", note).await;
+        } else {
+            let cache = ListCache::default();
+            managedobjects::cleanup(obj.as_ref(), self.client.clone(), &cache)
+                .await
+                .with_context(|| failed_part("cleaning up", "managed objects", obj.as_ref()))?;
+            if obj.spec.api_token_secret_ref.is_none() {
+                servicegroup::cleanup(obj.as_ref(), self.client.clone())
+                    .await
+                    .with_context(|| failed_part("cleaning up", "service group", obj.as_ref()))?;
+                serviceaccount::cleanup(obj.as_ref(), self.client.clone())
+                    .await
+                    .with_context(|| failed_part("cleaning up", "service account", obj.as_ref()))?;
+            }
+        }
+        if obj.spec.api_token_secret_ref.is_none() {
+            // The token is needed by the other parts, so the secret goes last.
+            secret::cleanup(obj.as_ref(), self.client.clone())
+                .await
+                .with_context(|| failed_part("cleaning up", "secret", obj.as_ref()))?;
+        }
+        autoscaler::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "autoscaler", obj.as_ref()))?;
+        ingress::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "ingress", obj.as_ref()))?;
+        service::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "service", obj.as_ref()))?;
+        deployment::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "deployment", obj.as_ref()))?;
+        clusteraccount::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "cluster account", obj.as_ref()))?;
 
         Ok(Action::await_change())
     }
 
+    // Reconcile all parts, stopping early with the readiness if the server isn't running yet.
+    async fn reconcile_parts(&self, obj: &crd::Authentik) -> Result<Option<status::Readiness>> {
+        // Refuse to deploy invalid configurations.
+        obj.spec.validate()?;
+        if let Err(e) = migration::check_downgrade(obj) {
+            publish_warning(self.client.clone(), obj, "Downgrade", e.to_string()).await;
+            return Err(e);
+        }
+        self.warn_floating_tags(obj).await;
+
+        // Reconcile all parts.
+        clusteraccount::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "cluster account", obj))?;
+        deployment::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "deployment", obj))?;
+        service::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "service", obj))?;
+        ingress::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "ingress", obj))?;
+        autoscaler::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "autoscaler", obj))?;
+
+        // Everything below talks to Authentik, which isn't running while suspended.
+        if obj.spec.suspended {
+            return Ok(None);
+        }
+
+        // The server is unavailable for a while during an install or upgrade, which is reported as
+        // migrating instead of as a failure. Once it is rolled out it should become ready quickly.
+        if !migration::server_rolled_out(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("checking", "server rollout", obj))?
+        {
+            return Ok(Some(status::Readiness::Migrating(
+                migration::ROLLOUT_MESSAGE.to_string(),
+            )));
+        }
+        let ns = obj
+            .namespace()
+            .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+        wait_for_ready(&obj.name_any(), &ns)
+            .await
+            .with_context(|| failed_part("waiting for", "server", obj))?;
+
+        // Fail early when the provided token is rejected, instead of with the first request using it.
+        if obj.spec.api_token_secret_ref.is_some() {
+            check_secret_token(self.client.clone(), &ns, &obj.name_any())
+                .await
+                .with_context(|| failed_part("checking", "API token", obj))?;
+        }
+
+        // The lists from Authentik are shared by the parts for the rest of this reconcile.
+        let cache = ListCache::default();
+        managedobjects::reconcile(obj, self.client.clone(), &cache)
+            .await
+            .with_context(|| failed_part("reconciling", "managed objects", obj))?;
+        diagnostics::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "diagnostics", obj))?;
+        smtp::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("testing", "SMTP connection", obj))?;
+        tokens::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "orphaned tokens", obj))?;
+        blueprints::reconcile(obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "blueprints", obj))?;
+
+        // The service account and its token are only bootstrapped if no token is provided.
+        if obj.spec.api_token_secret_ref.is_none() {
+            serviceaccount::reconcile(obj, self.client.clone())
+                .await
+                .with_context(|| failed_part("reconciling", "service account", obj))?;
+            servicegroup::reconcile(obj, self.client.clone())
+                .await
+                .with_context(|| failed_part("reconciling", "service group", obj))?;
+            secret::reconcile(obj, self.client.clone())
+                .await
+                .with_context(|| failed_part("reconciling", "secret", obj))?;
+        }
+
+        // Last, so it includes everything that was created above.
+        inventory::reconcile(obj, self.client.clone(), &cache)
+            .await
+            .with_context(|| failed_part("reconciling", "managed resources", obj))?;
+
+        Ok(None)
+    }
+
+    // Warn about image tags that move, as these make upgrades happen at random.
+    // This is only done once per generation, so the events aren't repeated on every reconcile.
+    async fn warn_floating_tags(&self, obj: &crd::Authentik) {
+        let reconciled = obj
+            .status
+            .as_ref()
+            .and_then(|status| status.history.last())
+            .is_some_and(|event| event.generation == obj.metadata.generation);
+        if reconciled {
+            return;
+        }
+
+        for (component, image) in [
+            ("server", obj.spec.server_image()),
+            ("worker", obj.spec.worker_image()),
+        ] {
+            if !image.tag.is_empty() && image.tag != "latest" {
+                continue;
+            }
+
+            let note = format!(
+                "The {} image `{}` uses the tag `{}`, pin it to a version like `2022.10.1` for reproducible upgrades.",
+                component, image.repository, image.tag
+            );
+            warn!("{}", note);
+            publish_warning(self.client.clone(), obj, "FloatingImageTag", note).await;
+        }
+    }
+
     async fn autofill(
         &self,
         obj: &mut crd::Authentik,
@@ -74,7 +296,7 @@ impl Controller {
     ) -> Result<bool> {
         let mut values = Map::new();
 
-        if obj.spec.secret_key.is_none() {
+        if obj.spec.secret_key.is_none() && obj.spec.secret_key_secret_ref.is_none() {
             // Create the secret if it does not yet exist
             let secret: String = thread_rng()
                 .sample_iter(&Alphanumeric)
@@ -1,7 +1,14 @@
+use std::{collections::BTreeMap, net::IpAddr};
+
+use chrono::{DateTime, Utc};
+use k8s_openapi::api::core::v1::{EnvVar, Volume, VolumeMount};
 use kube::CustomResource;
+use reqwest::Url;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::InvalidObj;
+
 #[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 #[kube(
@@ -10,22 +17,159 @@ use serde::{Deserialize, Serialize};
     version = "v1",
     plural = "authentik",
     shortname = "ak",
+    status = "AuthentikStatus",
+    printcolumn = r#"{"name":"Version", "type":"string", "jsonPath":".spec.image.tag"}"#,
+    printcolumn = r#"{"name":"Ready", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].status"}"#,
+    printcolumn = r#"{"name":"Reason", "type":"string", "jsonPath":".status.conditions[?(@.type==\"Ready\")].reason"}"#,
+    printcolumn = r#"{"name":"Age", "type":"date", "jsonPath":".metadata.creationTimestamp"}"#,
     namespaced
 )]
 pub struct AuthentikSpec {
+    /// The secret key for signing. It must be at least 50 characters long, which also applies to
+    /// the key read with `secretKeySecretRef`. Shorter keys fail the reconcile with an
+    /// `InvalidSecretKey` warning event.
     pub secret_key: Option<String>,
+    pub secret_key_secret_ref: Option<AuthentikSecretRef>,
     #[serde(default = "default_log_level")]
     pub log_level: String,
     #[serde(default = "default_avatars")]
     pub avatars: String,
     #[serde(default = "default_image")]
     pub image: AuthentikImage,
+    #[serde(default = "default_server")]
+    pub server: AuthentikServer,
+    #[serde(default = "default_worker")]
+    pub worker: AuthentikWorker,
+    // Deprecated in favor of `ui.footerLinks`, still accepted for existing objects.
     #[serde(default)]
     pub footer_links: Vec<AuthentikFooterLink>,
+    pub ui: Option<AuthentikUi>,
     pub ingress: Option<AuthentikIngress>,
+    pub metrics_ingress: Option<AuthentikMetricsIngress>,
     pub postgres: AuthentikPostgres,
     pub redis: AuthentikRedis,
     pub smtp: Option<AuthentikSmtp>,
+    pub cache: Option<AuthentikCache>,
+    pub wait_for_dependencies: Option<AuthentikWaitForDependencies>,
+    #[validate(range(min = 1))]
+    pub token_rotation_days: Option<u32>,
+    pub media: Option<AuthentikEmptyDir>,
+    #[serde(default)]
+    pub extra_volumes: Vec<Volume>,
+    #[serde(default)]
+    pub extra_volume_mounts: Vec<VolumeMount>,
+    /// Extra environment variables for the server and worker, added after the ones set by the
+    /// operator. When a name is repeated the last value wins, and it takes the position of the
+    /// first occurrence, so these can override the variables of the operator.
+    #[serde(default)]
+    pub extra_env: Vec<EnvVar>,
+    #[serde(default)]
+    pub pod_annotations: BTreeMap<String, String>,
+    pub api_token_secret_ref: Option<AuthentikSecretRef>,
+    #[serde(default)]
+    pub deletion_policy: DeletionPolicy,
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default)]
+    pub cleanup_orphaned_tokens: bool,
+    #[serde(default)]
+    pub blueprints: Vec<AuthentikBlueprint>,
+    pub runtime_class_name: Option<String>,
+    #[serde(default)]
+    pub share_process_namespace: bool,
+    pub scheduler_name: Option<String>,
+    pub dns_policy: Option<String>,
+    #[serde(default)]
+    pub enable_service_links: bool,
+    pub listen: Option<AuthentikListen>,
+    #[serde(default)]
+    pub host_network: bool,
+    pub proxy: Option<AuthentikProxy>,
+    pub outposts: Option<AuthentikOutposts>,
+    pub min_ready_seconds: Option<u32>,
+    #[validate(range(min = 1))]
+    pub progress_deadline_seconds: Option<u32>,
+    pub fs_group: Option<i64>,
+    pub service_account_name: Option<String>,
+    #[serde(default = "default_automount_service_account_token")]
+    pub automount_service_account_token: bool,
+    #[serde(default = "default_termination_message_policy")]
+    pub termination_message_policy: String,
+    #[serde(default)]
+    pub read_only_root_filesystem: bool,
+    #[serde(default = "default_diagnostics_command")]
+    #[validate(length(min = 1))]
+    pub diagnostics_command: Vec<String>,
+    #[serde(default)]
+    pub generated_metadata: AuthentikGeneratedMetadata,
+    #[serde(default)]
+    pub telemetry: AuthentikTelemetry,
+    pub gdpr_compliance: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikGeneratedMetadata {
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+}
+
+// Both are off by default, so nothing is sent to the Authentik developers unless asked for.
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikTelemetry {
+    #[serde(default)]
+    pub startup_analytics: bool,
+    #[serde(default)]
+    pub error_reporting: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikStatus {
+    #[serde(default)]
+    pub history: Vec<AuthentikReconcileEvent>,
+    #[serde(default)]
+    pub conditions: Vec<AuthentikCondition>,
+    pub force_reconcile: Option<String>,
+    pub diagnose: Option<String>,
+    pub version: Option<String>,
+    pub observed_generation: Option<i64>,
+    #[serde(default)]
+    pub managed_resources: AuthentikManagedResources,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikManagedResources {
+    // As `Kind/name`, with the namespace in between when it differs from that of the instance.
+    #[serde(default)]
+    pub kubernetes: Vec<String>,
+    // As `type/name`, or `type/pk` for objects that are not found by their name.
+    #[serde(default)]
+    pub authentik: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikCondition {
+    #[serde(rename = "type")]
+    pub condition_type: String,
+    pub status: String,
+    pub reason: String,
+    pub message: Option<String>,
+    pub last_transition_time: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikReconcileEvent {
+    pub timestamp: DateTime<Utc>,
+    pub generation: Option<i64>,
+    pub result: String,
+    pub message: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -35,13 +179,111 @@ pub struct AuthentikImage {
     pub repository: String,
     #[serde(default = "default_image_tag")]
     pub tag: String,
-    #[serde(default = "default_image_pullpolicy")]
-    pub pull_policy: String,
+    pub pull_policy: Option<String>,
+}
+
+impl AuthentikImage {
+    // Floating tags like `latest` have to be pulled every time to get updates.
+    pub fn pull_policy(&self) -> String {
+        if let Some(pull_policy) = self.pull_policy.as_ref() {
+            return pull_policy.clone();
+        }
+
+        if self.tag == "latest" || !self.tag.contains(|c: char| c.is_ascii_digit()) {
+            "Always".to_string()
+        } else {
+            "IfNotPresent".to_string()
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikServer {
+    pub image: Option<AuthentikImage>,
+    pub selector_match_labels: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    #[serde(default = "default_server_pre_stop")]
+    pub pre_stop: Option<Vec<String>>,
+    #[serde(default = "default_termination_grace_period")]
+    pub termination_grace_period: u32,
+    #[serde(default = "default_startup_probe")]
+    pub startup_probe: AuthentikStartupProbe,
+    #[serde(default)]
+    pub migration_init_container: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikStartupProbe {
+    #[serde(default = "default_startup_probe_failure_threshold")]
+    #[validate(range(min = 1))]
+    pub failure_threshold: u32,
+    #[serde(default = "default_startup_probe_period_seconds")]
+    #[validate(range(min = 1))]
+    pub period_seconds: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikWorker {
+    pub image: Option<AuthentikImage>,
+    pub selector_match_labels: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+    #[serde(default)]
+    pub pre_stop: Option<Vec<String>>,
+    #[serde(default = "default_termination_grace_period")]
+    pub termination_grace_period: u32,
+    pub autoscaling: Option<AuthentikAutoscaling>,
+    #[validate(range(min = 1))]
+    pub concurrency: Option<u32>,
+    #[validate(range(min = 1))]
+    pub max_tasks_per_child: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikAutoscaling {
+    #[serde(default = "default_autoscaling_min_replicas")]
+    #[validate(range(min = 1))]
+    pub min_replicas: u32,
+    #[validate(range(min = 1))]
+    pub max_replicas: u32,
+    #[validate(range(min = 1, max = 100))]
+    pub target_cpu_utilization: Option<u32>,
+    #[validate(range(min = 1, max = 100))]
+    pub target_memory_utilization: Option<u32>,
+    #[serde(default = "default_autoscaling_scale_down")]
+    pub scale_down: AuthentikAutoscalingScaleDown,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikAutoscalingScaleDown {
+    #[serde(default = "default_scale_down_stabilization_window")]
+    pub stabilization_window_seconds: u32,
+    #[serde(default = "default_scale_down_policies")]
+    pub policies: Vec<AuthentikAutoscalingPolicy>,
+    pub select_policy: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikAutoscalingPolicy {
+    #[serde(rename = "type")]
+    pub policy_type: String,
+    #[validate(range(min = 1))]
+    pub value: u32,
+    #[validate(range(min = 1))]
+    pub period_seconds: u32,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthentikFooterLink {
+    #[validate(length(min = 1))]
     pub name: String,
     #[validate(url)]
     pub href: String,
@@ -78,27 +320,138 @@ pub struct AuthentikIngressTLS {
     pub secret_name: String,
 }
 
+// Separate from the main ingress, so the metrics can be exposed on other hosts with their own auth.
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikMetricsIngress {
+    pub class_name: Option<String>,
+    pub rules: Vec<AuthentikIngressRule>,
+    #[serde(default)]
+    pub tls: Vec<AuthentikIngressTLS>,
+    pub basic_auth: AuthentikBasicAuth,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikBasicAuth {
+    #[validate(length(min = 1))]
+    pub secret_name: String,
+    #[serde(default = "default_basic_auth_realm")]
+    pub realm: String,
+}
+
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthentikPostgres {
-    pub host: String,
-    #[serde(default = "default_postgres_port")]
-    pub port: u16,
-    pub database: String,
-    pub username: String,
-    #[serde(default = "default_postgres_password")]
-    pub password: String,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub database: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
     pub password_secret: Option<String>,
     pub password_secret_key: Option<String>,
+    pub config_map_ref: Option<String>,
+    pub secret_ref: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct AuthentikRedis {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub password: Option<String>,
+    pub config_map_ref: Option<String>,
+    pub secret_ref: Option<String>,
+    pub sentinel: Option<AuthentikRedisSentinel>,
+    pub databases: Option<AuthentikRedisDatabases>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikRedisDatabases {
+    #[validate(range(max = 15))]
+    pub cache: Option<u8>,
+    #[validate(range(max = 15))]
+    pub message_queue: Option<u8>,
+    #[validate(range(max = 15))]
+    pub websockets: Option<u8>,
+    #[validate(range(max = 15))]
+    pub outpost_sessions: Option<u8>,
+}
+
+impl AuthentikRedisDatabases {
+    // The databases by the name of their field.
+    pub fn fields(&self) -> [(&'static str, Option<u8>); 4] {
+        [
+            ("cache", self.cache),
+            ("messageQueue", self.message_queue),
+            ("websockets", self.websockets),
+            ("outpostSessions", self.outpost_sessions),
+        ]
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikRedisSentinel {
+    #[validate(length(min = 1))]
+    pub hosts: Vec<AuthentikRedisSentinelHost>,
+    pub master_name: String,
+    #[serde(default)]
+    pub database: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikRedisSentinelHost {
     pub host: String,
-    #[serde(default = "default_redis_port")]
+    #[serde(default = "default_redis_sentinel_port")]
     pub port: u16,
-    pub password: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub enum DeletionPolicy {
+    #[default]
+    Delete,
+    Orphan,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikUi {
+    #[serde(default)]
+    pub footer_links: Vec<AuthentikFooterLink>,
+    pub impersonation: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikProxy {
+    #[serde(default)]
+    pub trusted_origins: Vec<String>,
+    #[serde(default)]
+    pub trusted_proxy_cidrs: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikOutposts {
+    pub container_image_base: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikCache {
+    pub url: Option<String>,
+    #[validate(range(min = 1))]
+    pub timeout: Option<u32>,
+    #[validate(range(min = 1))]
+    pub timeout_flows: Option<u32>,
+    #[validate(range(min = 1))]
+    pub timeout_policies: Option<u32>,
+    #[validate(range(min = 1))]
+    pub timeout_reputation: Option<u32>,
+    pub result_backend_url: Option<String>,
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
@@ -112,12 +465,477 @@ pub struct AuthentikSmtp {
     pub username: String,
     #[serde(default)]
     pub password: String,
+    pub encryption: Option<AuthentikSmtpEncryption>,
+    // Deprecated in favor of `encryption`, still accepted for existing objects.
     #[serde(default = "default_smtp_tls")]
     pub use_tls: bool,
     #[serde(default = "default_smtp_ssl")]
     pub use_ssl: bool,
     #[serde(default = "default_smtp_timeout")]
+    #[validate(range(min = 1))]
     pub timeout: u16,
+    #[serde(default)]
+    pub test_connection: bool,
+}
+
+impl AuthentikSmtp {
+    // The encryption, or the one from the old fields if it is not set.
+    pub fn encryption(&self) -> AuthentikSmtpEncryption {
+        match self.encryption {
+            Some(encryption) => encryption,
+            None if self.use_ssl => AuthentikSmtpEncryption::Ssl,
+            None if self.use_tls => AuthentikSmtpEncryption::StartTls,
+            None => AuthentikSmtpEncryption::None,
+        }
+    }
+}
+
+// StartTls upgrades a plain connection (usually port 587), Ssl uses TLS from the start (port 465).
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+pub enum AuthentikSmtpEncryption {
+    #[default]
+    None,
+    StartTls,
+    Ssl,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikWaitForDependencies {
+    #[serde(default = "default_wait_timeout")]
+    #[validate(range(min = 1))]
+    pub timeout_seconds: u32,
+    #[serde(default = "default_wait_poll_interval")]
+    #[validate(range(min = 1))]
+    pub poll_interval_seconds: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikEmptyDir {
+    #[serde(default = "default_empty_dir_size_limit")]
+    pub size_limit: String,
+    pub medium: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikSecretRef {
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[serde(default = "default_token_secret_key")]
+    pub key: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikBlueprint {
+    #[validate(length(min = 1))]
+    pub name: String,
+    pub content: Option<String>,
+    pub config_map_ref: Option<AuthentikConfigMapRef>,
+    pub url: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikConfigMapRef {
+    #[validate(length(min = 1))]
+    pub name: String,
+    #[serde(default = "default_blueprint_config_map_key")]
+    pub key: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikListen {
+    #[serde(default = "default_listen_address")]
+    pub address: String,
+    #[serde(default = "default_listen_http_port")]
+    #[validate(range(min = 1))]
+    pub http_port: u16,
+    #[serde(default = "default_listen_metrics_port")]
+    #[validate(range(min = 1))]
+    pub metrics_port: u16,
+    #[validate(range(min = 1))]
+    pub http_host_port: Option<u16>,
+    #[validate(range(min = 1))]
+    pub metrics_host_port: Option<u16>,
+}
+
+impl AuthentikSpec {
+    pub fn server_image(&self) -> &AuthentikImage {
+        self.server.image.as_ref().unwrap_or(&self.image)
+    }
+
+    pub fn worker_image(&self) -> &AuthentikImage {
+        self.worker.image.as_ref().unwrap_or(&self.image)
+    }
+
+    pub fn version(&self) -> String {
+        self.server_image().tag.clone()
+    }
+
+    // The service account of the pods, which is only created by the operator if none is given.
+    pub fn service_account_name(&self, instance: &str) -> String {
+        self.service_account_name
+            .clone()
+            .unwrap_or_else(|| format!("ak-{}", instance))
+    }
+
+    pub fn http_port(&self) -> u16 {
+        self.listen
+            .as_ref()
+            .map(|listen| listen.http_port)
+            .unwrap_or_else(default_listen_http_port)
+    }
+
+    // Volumes mounted by the operator are only writable by Authentik with the right group.
+    pub fn fs_group(&self) -> Option<i64> {
+        self.fs_group
+            .or_else(|| self.media.as_ref().map(|_| default_fs_group()))
+    }
+
+    // The footer links from the UI section, or from the old field if these are not set there.
+    pub fn footer_links(&self) -> &[AuthentikFooterLink] {
+        match self.ui.as_ref() {
+            Some(ui) if !ui.footer_links.is_empty() => &ui.footer_links,
+            _ => &self.footer_links,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), InvalidObj> {
+        if self.secret_key.is_some() && self.secret_key_secret_ref.is_some() {
+            return Err(InvalidObj::Exclusive(
+                "secretKey".to_string(),
+                "secretKeySecretRef".to_string(),
+            ));
+        }
+
+        let ui_footer_links = self.ui.as_ref().map_or(&[][..], |ui| &ui.footer_links[..]);
+        if !ui_footer_links.is_empty() && !self.footer_links.is_empty() {
+            return Err(InvalidObj::Exclusive(
+                "ui.footerLinks".to_string(),
+                "footerLinks".to_string(),
+            ));
+        }
+
+        let field = match ui_footer_links.is_empty() {
+            true => "footerLinks",
+            false => "ui.footerLinks",
+        };
+        for (i, link) in self.footer_links().iter().enumerate() {
+            if link.name.is_empty() {
+                return Err(InvalidObj::InvalidField(
+                    format!("{}[{}].name", field, i),
+                    "it cannot be empty".to_string(),
+                ));
+            }
+            if let Err(e) = Url::parse(&link.href) {
+                return Err(InvalidObj::InvalidField(
+                    format!("{}[{}].href", field, i),
+                    format!("`{}` is not a valid url: {}", link.href, e),
+                ));
+            }
+        }
+
+        for (i, blueprint) in self.blueprints.iter().enumerate() {
+            if self.blueprints[..i]
+                .iter()
+                .any(|other| other.name == blueprint.name)
+            {
+                return Err(InvalidObj::InvalidField(
+                    format!("blueprints[{}].name", i),
+                    format!("`{}` is used by another blueprint", blueprint.name),
+                ));
+            }
+
+            let sources: Vec<&str> = [
+                ("content", blueprint.content.is_some()),
+                ("configMapRef", blueprint.config_map_ref.is_some()),
+                ("url", blueprint.url.is_some()),
+            ]
+            .into_iter()
+            .filter(|(_, set)| *set)
+            .map(|(source, _)| source)
+            .collect();
+            match sources[..] {
+                [] => {
+                    return Err(InvalidObj::InvalidField(
+                        format!("blueprints[{}]", i),
+                        "one of `content`, `configMapRef` or `url` has to be set".to_string(),
+                    ))
+                }
+                [_] => {}
+                [first, second, ..] => {
+                    return Err(InvalidObj::Exclusive(
+                        format!("blueprints[{}].{}", i, first),
+                        format!("blueprints[{}].{}", i, second),
+                    ))
+                }
+            }
+
+            if let Some(Err(e)) = blueprint.url.as_ref().map(|url| Url::parse(url)) {
+                return Err(InvalidObj::InvalidField(
+                    format!("blueprints[{}].url", i),
+                    format!("it is not a valid url: {}", e),
+                ));
+            }
+        }
+
+        if let Some(smtp) = self.smtp.as_ref() {
+            if smtp.timeout == 0 {
+                return Err(InvalidObj::InvalidField(
+                    "smtp.timeout".to_string(),
+                    "it has to be a positive number".to_string(),
+                ));
+            }
+            // SSL wraps the whole connection while TLS upgrades it with STARTTLS, so only one can be used.
+            if smtp.use_tls && smtp.use_ssl {
+                return Err(InvalidObj::Exclusive(
+                    "smtp.useTls".to_string(),
+                    "smtp.useSsl".to_string(),
+                ));
+            }
+            if smtp.encryption.is_some() && (smtp.use_tls || smtp.use_ssl) {
+                let field = if smtp.use_tls {
+                    "smtp.useTls"
+                } else {
+                    "smtp.useSsl"
+                };
+                return Err(InvalidObj::Exclusive(
+                    "smtp.encryption".to_string(),
+                    field.to_string(),
+                ));
+            }
+        }
+
+        if let Some(proxy) = self.proxy.as_ref() {
+            for (i, origin) in proxy.trusted_origins.iter().enumerate() {
+                // An origin is only the scheme and host, so it must be a url without a path.
+                let valid = Url::parse(origin).map(|url| url.has_host() && url.path() == "/");
+                if !valid.unwrap_or(false) || origin.ends_with('/') {
+                    return Err(InvalidObj::InvalidField(
+                        format!("proxy.trustedOrigins[{}]", i),
+                        format!("`{}` is not an origin like `https://example.com`", origin),
+                    ));
+                }
+            }
+
+            for (i, cidr) in proxy.trusted_proxy_cidrs.iter().enumerate() {
+                if !is_cidr(cidr) {
+                    return Err(InvalidObj::InvalidField(
+                        format!("proxy.trustedProxyCidrs[{}]", i),
+                        format!("`{}` is not a CIDR like `10.0.0.0/8`", cidr),
+                    ));
+                }
+            }
+        }
+
+        // The metrics listener is only exposed on the pods when the listeners are configured.
+        if self.metrics_ingress.is_some() && self.listen.is_none() {
+            return Err(InvalidObj::MissingField(
+                "listen".to_string(),
+                "metricsIngress".to_string(),
+            ));
+        }
+
+        if let Some(listen) = self.listen.as_ref() {
+            if listen.http_port == listen.metrics_port {
+                return Err(InvalidObj::Conflict(
+                    "listen.httpPort".to_string(),
+                    "listen.metricsPort".to_string(),
+                ));
+            }
+
+            // On the host network the pod binds the ports of the node directly, so these have to match.
+            for (field, host_port, port) in [
+                (
+                    "listen.httpHostPort",
+                    listen.http_host_port,
+                    listen.http_port,
+                ),
+                (
+                    "listen.metricsHostPort",
+                    listen.metrics_host_port,
+                    listen.metrics_port,
+                ),
+            ] {
+                match host_port {
+                    Some(0) => {
+                        return Err(InvalidObj::InvalidField(
+                            field.to_string(),
+                            "it must be a port between 1 and 65535".to_string(),
+                        ))
+                    }
+                    Some(host_port) if self.host_network && host_port != port => {
+                        return Err(InvalidObj::InvalidField(
+                            field.to_string(),
+                            format!("it must be equal to the port ({}) with `hostNetwork`", port),
+                        ))
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        for (field, args) in [
+            ("server.extraArgs", &self.server.extra_args),
+            ("worker.extraArgs", &self.worker.extra_args),
+        ] {
+            if let Some(i) = args.iter().position(|arg| arg.is_empty()) {
+                return Err(InvalidObj::InvalidField(
+                    format!("{}[{}]", field, i),
+                    "it cannot be empty".to_string(),
+                ));
+            }
+        }
+
+        // Kubernetes rejects a deadline that passes before a pod could become available.
+        if let Some(deadline) = self.progress_deadline_seconds {
+            if deadline <= self.min_ready_seconds.unwrap_or(0) {
+                return Err(InvalidObj::InvalidField(
+                    "progressDeadlineSeconds".to_string(),
+                    "it has to be greater than `minReadySeconds`".to_string(),
+                ));
+            }
+        }
+
+        // The volumes added by the operator, so their names cannot be used again.
+        for (i, volume) in self.extra_volumes.iter().enumerate() {
+            if self.media.is_some() && volume.name == "media" {
+                return Err(InvalidObj::InvalidField(
+                    format!("extraVolumes[{}].name", i),
+                    "`media` is already used by the media volume".to_string(),
+                ));
+            }
+            if self.read_only_root_filesystem && volume.name == "tmp" {
+                return Err(InvalidObj::InvalidField(
+                    format!("extraVolumes[{}].name", i),
+                    "`tmp` is already used by the writable `/tmp` volume".to_string(),
+                ));
+            }
+        }
+
+        // The operator sets its own annotations on the pods, and these would replace the given ones.
+        if let Some(name) = self
+            .pod_annotations
+            .keys()
+            .find(|name| name.starts_with("ak.dany.dev/"))
+        {
+            return Err(InvalidObj::InvalidField(
+                format!("podAnnotations.{}", name),
+                "the `ak.dany.dev/` prefix is used by the operator".to_string(),
+            ));
+        }
+
+        let cache = self.cache.as_ref();
+        for (field, value) in [
+            ("worker.concurrency", self.worker.concurrency),
+            ("worker.maxTasksPerChild", self.worker.max_tasks_per_child),
+            ("cache.timeout", cache.and_then(|cache| cache.timeout)),
+            (
+                "cache.timeoutFlows",
+                cache.and_then(|cache| cache.timeout_flows),
+            ),
+            (
+                "cache.timeoutPolicies",
+                cache.and_then(|cache| cache.timeout_policies),
+            ),
+            (
+                "cache.timeoutReputation",
+                cache.and_then(|cache| cache.timeout_reputation),
+            ),
+        ] {
+            if value == Some(0) {
+                return Err(InvalidObj::InvalidField(
+                    field.to_string(),
+                    "it has to be a positive number".to_string(),
+                ));
+            }
+        }
+
+        // Sentinel replaces the host and port, so these would be ignored.
+        if self.redis.sentinel.is_some() {
+            if self.redis.host.is_some() {
+                return Err(InvalidObj::Exclusive(
+                    "redis.sentinel".to_string(),
+                    "redis.host".to_string(),
+                ));
+            }
+            if self.redis.port.is_some() {
+                return Err(InvalidObj::Exclusive(
+                    "redis.sentinel".to_string(),
+                    "redis.port".to_string(),
+                ));
+            }
+        }
+
+        // Redis has 16 databases by default, and sharing one defeats the point of setting these.
+        if let Some(databases) = self.redis.databases.as_ref() {
+            if self.redis.sentinel.is_some() {
+                return Err(InvalidObj::Exclusive(
+                    "redis.databases".to_string(),
+                    "redis.sentinel".to_string(),
+                ));
+            }
+
+            let fields = databases.fields();
+            for (i, (field, database)) in fields.iter().enumerate() {
+                let database = match database {
+                    Some(database) => database,
+                    None => continue,
+                };
+                if *database > 15 {
+                    return Err(InvalidObj::InvalidField(
+                        format!("redis.databases.{}", field),
+                        "it has to be between 0 and 15".to_string(),
+                    ));
+                }
+                if let Some((other, _)) = fields[..i]
+                    .iter()
+                    .find(|(_, other)| other.as_ref() == Some(database))
+                {
+                    return Err(InvalidObj::Conflict(
+                        format!("redis.databases.{}", other),
+                        format!("redis.databases.{}", field),
+                    ));
+                }
+            }
+        }
+
+        // The password secret is only used if both the name and key are given.
+        match (
+            &self.postgres.password_secret,
+            &self.postgres.password_secret_key,
+        ) {
+            (Some(_), None) => Err(InvalidObj::MissingField(
+                "postgres.passwordSecretKey".to_string(),
+                "postgres.passwordSecret".to_string(),
+            )),
+            (None, Some(_)) => Err(InvalidObj::MissingField(
+                "postgres.passwordSecret".to_string(),
+                "postgres.passwordSecretKey".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn is_cidr(cidr: &str) -> bool {
+    let (address, prefix) = match cidr.split_once('/') {
+        Some(parts) => parts,
+        None => return false,
+    };
+    let max_prefix = match address.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+
+    prefix
+        .parse::<u8>()
+        .is_ok_and(|prefix| prefix <= max_prefix)
 }
 
 // -- Default value functions from here on.
@@ -133,36 +951,107 @@ fn default_image() -> AuthentikImage {
     AuthentikImage {
         repository: default_image_repo(),
         tag: default_image_tag(),
-        pull_policy: default_image_pullpolicy(),
+        pull_policy: None,
     }
 }
 
-fn default_image_repo() -> String {
-    "ghcr.io/goauthentik/server".to_string()
+fn default_server() -> AuthentikServer {
+    AuthentikServer {
+        image: None,
+        selector_match_labels: None,
+        extra_args: Vec::new(),
+        pre_stop: default_server_pre_stop(),
+        termination_grace_period: default_termination_grace_period(),
+        startup_probe: default_startup_probe(),
+        migration_init_container: false,
+    }
 }
 
-fn default_image_tag() -> String {
-    "latest".to_string()
+fn default_startup_probe() -> AuthentikStartupProbe {
+    AuthentikStartupProbe {
+        failure_threshold: default_startup_probe_failure_threshold(),
+        period_seconds: default_startup_probe_period_seconds(),
+    }
 }
 
-fn default_image_pullpolicy() -> String {
-    "IfNotPresent".to_string()
+// Enough for the migrations of a major upgrade, which run before the server becomes live.
+fn default_startup_probe_failure_threshold() -> u32 {
+    90
 }
 
-fn default_ingress_path_type() -> String {
-    "ImplementationSpecific".to_string()
+fn default_startup_probe_period_seconds() -> u32 {
+    10
 }
 
-fn default_postgres_port() -> u16 {
-    5432
+fn default_worker() -> AuthentikWorker {
+    AuthentikWorker {
+        image: None,
+        selector_match_labels: None,
+        extra_args: Vec::new(),
+        pre_stop: None,
+        termination_grace_period: default_termination_grace_period(),
+        autoscaling: None,
+        concurrency: None,
+        max_tasks_per_child: None,
+    }
+}
+
+fn default_server_pre_stop() -> Option<Vec<String>> {
+    // Give the load balancer some time to stop sending new requests.
+    Some(vec!["sleep".to_string(), "10".to_string()])
 }
 
-fn default_postgres_password() -> String {
-    "postgres".to_string()
+fn default_termination_grace_period() -> u32 {
+    30
 }
 
-fn default_redis_port() -> u16 {
-    6379
+fn default_autoscaling_min_replicas() -> u32 {
+    1
+}
+
+fn default_autoscaling_scale_down() -> AuthentikAutoscalingScaleDown {
+    AuthentikAutoscalingScaleDown {
+        stabilization_window_seconds: default_scale_down_stabilization_window(),
+        policies: default_scale_down_policies(),
+        select_policy: None,
+    }
+}
+
+fn default_scale_down_stabilization_window() -> u32 {
+    600
+}
+
+fn default_scale_down_policies() -> Vec<AuthentikAutoscalingPolicy> {
+    // Remove at most one worker every five minutes, so running tasks have time to finish.
+    vec![AuthentikAutoscalingPolicy {
+        policy_type: "Pods".to_string(),
+        value: 1,
+        period_seconds: 300,
+    }]
+}
+
+fn default_empty_dir_size_limit() -> String {
+    "1Gi".to_string()
+}
+
+fn default_token_secret_key() -> String {
+    "token".to_string()
+}
+
+fn default_image_repo() -> String {
+    "ghcr.io/goauthentik/server".to_string()
+}
+
+fn default_image_tag() -> String {
+    "latest".to_string()
+}
+
+fn default_basic_auth_realm() -> String {
+    "Authentik metrics".to_string()
+}
+
+fn default_ingress_path_type() -> String {
+    "ImplementationSpecific".to_string()
 }
 
 fn default_smtp_port() -> u16 {
@@ -180,3 +1069,47 @@ fn default_smtp_ssl() -> bool {
 fn default_smtp_timeout() -> u16 {
     10
 }
+
+fn default_wait_timeout() -> u32 {
+    300
+}
+
+fn default_wait_poll_interval() -> u32 {
+    2
+}
+
+fn default_listen_address() -> String {
+    "0.0.0.0".to_string()
+}
+
+fn default_listen_http_port() -> u16 {
+    9000
+}
+
+fn default_listen_metrics_port() -> u16 {
+    9300
+}
+
+fn default_fs_group() -> i64 {
+    1000
+}
+
+fn default_automount_service_account_token() -> bool {
+    true
+}
+
+fn default_diagnostics_command() -> Vec<String> {
+    vec!["ak".to_string(), "dump_config".to_string()]
+}
+
+fn default_termination_message_policy() -> String {
+    "FallbackToLogsOnError".to_string()
+}
+
+fn default_redis_sentinel_port() -> u16 {
+    26379
+}
+
+fn default_blueprint_config_map_key() -> String {
+    "blueprint.yaml".to_string()
+}
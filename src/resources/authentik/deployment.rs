@@ -1,17 +1,25 @@
+use std::collections::BTreeMap;
+
 use anyhow::{anyhow, Result};
-use k8s_openapi::api::{
-    apps::v1::Deployment,
-    core::v1::{EnvVar, EnvVarSource, SecretKeySelector},
-};
-use kube::{
-    api::{Patch, PatchParams},
-    Api, Client, ResourceExt,
-};
+use k8s_openapi::api::{apps::v1::Deployment, core::v1::EnvVar};
+use kube::{Api, Client, ResourceExt};
 use serde_json::{json, Value};
 
-use crate::akapi::auth::TEMP_AUTH_TOKEN;
+use crate::{
+    akapi::auth::TEMP_AUTH_TOKEN,
+    resources::{apply_if_changed, hash_value, publish_warning},
+    InvalidObj,
+};
 
-use super::{crd, labels};
+use super::{
+    connection::{self, Connection, RedisEndpoint},
+    crd, labels,
+};
+
+static OPERATOR_VERSION: &str = env!("CARGO_PKG_VERSION");
+static OPERATOR_VERSION_ANNOTATION: &str = "ak.dany.dev/operator-version";
+static CONFIG_HASH_ANNOTATION: &str = "ak.dany.dev/config-hash";
+static SECRET_KEY_HASH_ANNOTATION: &str = "ak.dany.dev/secret-key-hash";
 
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let instance = obj
@@ -22,39 +30,99 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let conn = match connection::resolve(&obj.spec, client.clone(), &ns).await {
+        Ok(conn) => conn,
+        Err(e) => {
+            // Older versions accepted short secret keys, so make it visible why these now fail.
+            if let Some(invalid) = e.downcast_ref::<InvalidObj>() {
+                publish_warning(client, obj, "InvalidSecretKey", invalid.to_string()).await;
+            }
+            return Err(e);
+        }
+    };
 
     // Create the server deployment.
     let api: Api<Deployment> = Api::namespaced(client.clone(), &ns);
-    api.patch(
+    warn_secret_key_change(
+        obj,
+        &api,
         &format!("authentik-{}-server", instance),
-        &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_server(instance.clone(), obj)),
+        &conn,
+        client.clone(),
+    )
+    .await?;
+    apply_if_changed(
+        &api,
+        &format!("authentik-{}-server", instance),
+        build_server(instance.clone(), obj, &conn),
     )
     .await?;
 
     // Create the worker deployment.
     let api: Api<Deployment> = Api::namespaced(client, &ns);
-    api.patch(
+    apply_if_changed(
+        &api,
         &format!("authentik-{}-worker", instance),
-        &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build_worker(instance.clone(), obj)),
+        build_worker(instance.clone(), obj, &conn),
     )
     .await?;
 
     Ok(())
 }
 
+// Changing the secret key logs out every user, so make sure this doesn't go unnoticed.
+async fn warn_secret_key_change(
+    obj: &crd::Authentik,
+    api: &Api<Deployment>,
+    name: &str,
+    conn: &Connection,
+    client: Client,
+) -> Result<()> {
+    let live = api.get_opt(name).await?;
+    let previous = live
+        .as_ref()
+        .and_then(|deployment| deployment.spec.as_ref())
+        .and_then(|spec| spec.template.metadata.as_ref())
+        .and_then(|metadata| metadata.annotations.as_ref())
+        .and_then(|annotations| annotations.get(SECRET_KEY_HASH_ANNOTATION));
+
+    // Older versions of the operator used a shorter hash, which says nothing about the key itself.
+    let changed = previous.is_some_and(|previous| {
+        previous.len() == conn.secret_key.hash.len() && previous != &conn.secret_key.hash
+    });
+    if changed {
+        publish_warning(
+            client,
+            obj,
+            "SecretKeyChanged",
+            "The secret key changed, the pods are restarted and all existing sessions are invalidated."
+                .to_string(),
+        )
+        .await;
+    }
+
+    Ok(())
+}
+
 pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
     Ok(())
 }
 
-fn build_server(name: String, obj: &crd::Authentik) -> Value {
-    json!({
+fn build_server(name: String, obj: &crd::Authentik, conn: &Connection) -> Value {
+    let image = obj.spec.server_image();
+    let (selector, pod_labels) = build_selector(
+        name.clone(),
+        image,
+        "server",
+        obj.spec.server.selector_match_labels.as_ref(),
+    );
+
+    let mut deployment = json!({
         "apiVersion": "apps/v1",
         "kind": "Deployment",
         "metadata": {
             "name": format!("authentik-{}-server", name.clone()),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "server".to_string()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "server".to_string()),
             "ownerReferences": [{
                 "apiVersion": "ak.dany.dev/v1",
                 "kind": "Authentik",
@@ -64,30 +132,34 @@ fn build_server(name: String, obj: &crd::Authentik) -> Value {
             }]
         },
         "spec": {
-            "replicas": 1,
+            "replicas": if obj.spec.suspended { 0 } else { 1 },
             "selector": {
-                "matchLabels": labels::get_matching_labels(name.clone(), "server".to_string())
+                "matchLabels": selector
             },
             "template": {
                 "metadata": {
-                    "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "server".to_string()),
+                    "labels": pod_labels,
+                    "annotations": obj.spec.pod_annotations,
                 },
                 "spec": {
-                    "serviceAccountName": format!("ak-{}", name),
-                    "enableServiceLinks": true,
+                    "serviceAccountName": obj.spec.service_account_name(&name),
+                    "automountServiceAccountToken": obj.spec.automount_service_account_token,
+                    "terminationGracePeriodSeconds": obj.spec.server.termination_grace_period,
+                    "initContainers": build_init_containers(&obj.spec, conn, image),
+                    "volumes": build_volumes(&obj.spec),
                     "containers": [{
-                        "name": format!("authentik-{}-server", name),
-                        "image": format!("{}:{}", obj.spec.image.repository, obj.spec.image.tag),
-                        "imagePullPolicy": obj.spec.image.pull_policy,
-                        "args": ["server"],
-                        "ports": [{
-                            "name": "http",
-                            "containerPort": 9000,
-                            "protocol": "TCP"
-                        }],
+                        "name": "server",
+                        "image": format!("{}:{}", image.repository, image.tag),
+                        "imagePullPolicy": image.pull_policy(),
+                        "args": build_args("server", &obj.spec.server.extra_args),
+                        "terminationMessagePolicy": obj.spec.termination_message_policy,
+                        "securityContext": build_security_context(&obj.spec),
+                        "ports": build_ports(&obj.spec),
+                        // Only the server receives traffic, so only its readiness gates the service.
+                        // The migrations run before the server goes live, so give these plenty of time.
                         "startupProbe": {
-                            "failureThreshold": 30,
-                            "periodSeconds": 10,
+                            "failureThreshold": obj.spec.server.startup_probe.failure_threshold,
+                            "periodSeconds": obj.spec.server.startup_probe.period_seconds,
                             "httpGet": {
                                 "path": "/-/health/live/",
                                 "port": "http"
@@ -109,21 +181,57 @@ fn build_server(name: String, obj: &crd::Authentik) -> Value {
                                 "port": "http"
                             }
                         },
-                        "env": build_env(&obj.spec)
+                        "lifecycle": build_lifecycle(obj.spec.server.pre_stop.as_ref()),
+                        "volumeMounts": build_volume_mounts(&obj.spec),
+                        "env": build_env_server(&obj.spec, conn)
                     }]
                 }
             }
         }
-    })
+    });
+
+    // Migrate after waiting for the dependencies, as the migrations need the database.
+    if obj.spec.server.migration_init_container {
+        if let Some(init_containers) =
+            deployment["spec"]["template"]["spec"]["initContainers"].as_array_mut()
+        {
+            init_containers.push(build_migration_container(&obj.spec, conn, image));
+        }
+    }
+
+    set_deployment_options(&mut deployment["spec"], &obj.spec);
+    set_pod_options(&mut deployment["spec"]["template"]["spec"], &obj.spec);
+
+    // Only the server listens, so the worker stays on the pod network.
+    // The cluster DNS is still used unless another policy is given, as the database and Redis are
+    // usually services.
+    if obj.spec.host_network {
+        deployment["spec"]["template"]["spec"]["hostNetwork"] = json!(true);
+        if obj.spec.dns_policy.is_none() {
+            deployment["spec"]["template"]["spec"]["dnsPolicy"] = json!("ClusterFirstWithHostNet");
+        }
+    }
+
+    set_annotations(&mut deployment, conn);
+
+    deployment
 }
 
-fn build_worker(name: String, obj: &crd::Authentik) -> Value {
-    json!({
+fn build_worker(name: String, obj: &crd::Authentik, conn: &Connection) -> Value {
+    let image = obj.spec.worker_image();
+    let (selector, pod_labels) = build_selector(
+        name.clone(),
+        image,
+        "worker",
+        obj.spec.worker.selector_match_labels.as_ref(),
+    );
+
+    let mut deployment = json!({
         "apiVersion": "apps/v1",
         "kind": "Deployment",
         "metadata": {
             "name": format!("authentik-{}-worker", name.clone()),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "worker".to_string()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "worker".to_string()),
             "ownerReferences": [{
                 "apiVersion": "ak.dany.dev/v1",
                 "kind": "Authentik",
@@ -133,131 +241,598 @@ fn build_worker(name: String, obj: &crd::Authentik) -> Value {
             }]
         },
         "spec": {
-            "replicas": 1,
             "selector": {
-                "matchLabels": labels::get_matching_labels(name.clone(), "worker".to_string())
+                "matchLabels": selector
             },
             "template": {
                 "metadata": {
-                    "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "worker".to_string()),
+                    "labels": pod_labels,
+                    "annotations": obj.spec.pod_annotations,
                 },
                 "spec": {
-                    "serviceAccountName": format!("ak-{}", name),
-                    "enableServiceLinks": true,
+                    "serviceAccountName": obj.spec.service_account_name(&name),
+                    "automountServiceAccountToken": obj.spec.automount_service_account_token,
+                    "terminationGracePeriodSeconds": obj.spec.worker.termination_grace_period,
+                    "initContainers": build_init_containers(&obj.spec, conn, image),
+                    "volumes": build_volumes(&obj.spec),
                     "containers": [{
-                        "name": format!("authentik-{}-worker", name),
-                        "image": format!("{}:{}", obj.spec.image.repository, obj.spec.image.tag),
-                        "imagePullPolicy": obj.spec.image.pull_policy,
-                        "args": ["worker"],
-                        "env": build_env(&obj.spec)
+                        "name": "worker",
+                        "image": format!("{}:{}", image.repository, image.tag),
+                        "imagePullPolicy": image.pull_policy(),
+                        "args": build_args("worker", &obj.spec.worker.extra_args),
+                        "terminationMessagePolicy": obj.spec.termination_message_policy,
+                        "securityContext": build_security_context(&obj.spec),
+                        // The worker serves no traffic, so it only gets a liveness check.
+                        // This restarts a stuck worker without affecting the readiness of the server.
+                        "startupProbe": {
+                            "failureThreshold": 30,
+                            "periodSeconds": 10,
+                            "exec": {
+                                "command": ["ak", "healthcheck"]
+                            }
+                        },
+                        "livenessProbe": {
+                            "failureThreshold": 3,
+                            "periodSeconds": 30,
+                            "exec": {
+                                "command": ["ak", "healthcheck"]
+                            }
+                        },
+                        "lifecycle": build_lifecycle(obj.spec.worker.pre_stop.as_ref()),
+                        "volumeMounts": build_volume_mounts(&obj.spec),
+                        "env": build_env_worker(&obj.spec, conn)
                     }]
                 }
             }
         }
+    });
+
+    // Leave the replicas to the autoscaler if it's enabled.
+    if obj.spec.suspended {
+        deployment["spec"]["replicas"] = json!(0);
+    } else if obj.spec.worker.autoscaling.is_none() {
+        deployment["spec"]["replicas"] = json!(1);
+    }
+
+    set_deployment_options(&mut deployment["spec"], &obj.spec);
+    set_pod_options(&mut deployment["spec"]["template"]["spec"], &obj.spec);
+    set_annotations(&mut deployment, conn);
+
+    deployment
+}
+
+// Get the selector and the labels of the pods, which have to include the selector.
+// The selector can be overridden to adopt an existing deployment, as it cannot be changed later on.
+// The pods are labeled with the version of their own image, so the pod template of a deployment only
+// changes when its image does. Changes to the labels of the deployment itself don't roll out new pods.
+fn build_selector(
+    name: String,
+    image: &crd::AuthentikImage,
+    component: &str,
+    selector_override: Option<&BTreeMap<String, String>>,
+) -> (BTreeMap<String, String>, BTreeMap<String, String>) {
+    let selector = match selector_override {
+        Some(selector) => selector.clone(),
+        None => labels::get_matching_labels(name.clone(), component.to_string()),
+    };
+
+    let mut pod_labels = labels::get_labels(name, image.tag.clone(), component.to_string());
+    pod_labels.extend(selector.clone());
+
+    (selector, pod_labels)
+}
+
+// Record which operator version built the deployment and a hash of the pods it results in.
+// Only the hash goes on the pods, as the version would restart them on every upgrade of the operator.
+fn set_annotations(deployment: &mut Value, conn: &Connection) {
+    // The secret key might be read from a secret, so its hash is included to restart the pods when it changes.
+    deployment["spec"]["template"]["metadata"]["annotations"][SECRET_KEY_HASH_ANNOTATION] =
+        json!(conn.secret_key.hash);
+    let hash = hash_value(&deployment["spec"]["template"]);
+
+    deployment["metadata"]["annotations"][OPERATOR_VERSION_ANNOTATION] = json!(OPERATOR_VERSION);
+    deployment["metadata"]["annotations"][CONFIG_HASH_ANNOTATION] = json!(hash);
+    deployment["spec"]["template"]["metadata"]["annotations"][CONFIG_HASH_ANNOTATION] = json!(hash);
+}
+
+// Set the optional fields of the deployment spec, these are left out entirely when not configured.
+fn set_deployment_options(deployment: &mut Value, obj: &crd::AuthentikSpec) {
+    if let Some(min_ready_seconds) = obj.min_ready_seconds {
+        deployment["minReadySeconds"] = json!(min_ready_seconds);
+    }
+    if let Some(progress_deadline_seconds) = obj.progress_deadline_seconds {
+        deployment["progressDeadlineSeconds"] = json!(progress_deadline_seconds);
+    }
+}
+
+// Set the optional fields of the pod spec, these are left out entirely when not configured.
+pub fn set_pod_options(pod: &mut Value, obj: &crd::AuthentikSpec) {
+    // The variables for the services in the namespace could collide with those of Authentik, so
+    // these are only injected when asked for.
+    pod["enableServiceLinks"] = json!(obj.enable_service_links);
+
+    if let Some(runtime_class_name) = obj.runtime_class_name.as_ref() {
+        pod["runtimeClassName"] = json!(runtime_class_name);
+    }
+
+    if obj.share_process_namespace {
+        pod["shareProcessNamespace"] = json!(true);
+    }
+
+    if let Some(scheduler_name) = obj.scheduler_name.as_ref() {
+        pod["schedulerName"] = json!(scheduler_name);
+    }
+
+    if let Some(dns_policy) = obj.dns_policy.as_ref() {
+        pod["dnsPolicy"] = json!(dns_policy);
+    }
+
+    if let Some(fs_group) = obj.fs_group() {
+        pod["securityContext"] = json!({ "fsGroup": fs_group });
+    }
+}
+
+fn build_ports(obj: &crd::AuthentikSpec) -> Vec<Value> {
+    let mut ports = vec![json!({
+        "name": "http",
+        "containerPort": obj.http_port(),
+        "protocol": "TCP"
+    })];
+
+    if let Some(listen) = obj.listen.as_ref() {
+        ports.push(json!({
+            "name": "metrics",
+            "containerPort": listen.metrics_port,
+            "protocol": "TCP"
+        }));
+
+        if let Some(host_port) = listen.http_host_port {
+            ports[0]["hostPort"] = json!(host_port);
+        }
+        if let Some(host_port) = listen.metrics_host_port {
+            ports[1]["hostPort"] = json!(host_port);
+        }
+    }
+
+    ports
+}
+
+fn build_init_containers(
+    obj: &crd::AuthentikSpec,
+    conn: &Connection,
+    image: &crd::AuthentikImage,
+) -> Vec<Value> {
+    let wait = match obj.wait_for_dependencies.as_ref() {
+        Some(wait) => wait,
+        None => return vec![],
+    };
+
+    // Wait until all the given host and port pairs accept TCP connections, or fail after the timeout.
+    let script = format!(
+        r#"deadline=$(( $(date +%s) + {timeout} ))
+while [ "$#" -gt 0 ]; do
+    until (exec 3<>"/dev/tcp/$1/$2") 2>/dev/null; do
+        if [ "$(date +%s)" -ge "$deadline" ]; then
+            echo "Timed out after {timeout} seconds waiting for $1:$2 to become available." >&2
+            exit 1
+        fi
+        echo "Waiting for $1:$2..."
+        sleep {interval}
+    done
+    shift 2
+done"#,
+        timeout = wait.timeout_seconds,
+        interval = wait.poll_interval_seconds,
+    );
+
+    let mut command = vec![
+        "bash".to_string(),
+        "-c".to_string(),
+        script,
+        "wait-for-dependencies".to_string(),
+        conn.postgres.host.clone(),
+        conn.postgres.port.to_string(),
+    ];
+    match &conn.redis.endpoint {
+        RedisEndpoint::Standalone(host, port) => {
+            command.extend([host.clone(), port.to_string()]);
+        }
+        RedisEndpoint::Sentinel(sentinel) => {
+            for host in sentinel.hosts.iter() {
+                command.extend([host.host.clone(), host.port.to_string()]);
+            }
+        }
+    }
+
+    vec![json!({
+        "name": "wait-for-dependencies",
+        "image": format!("{}:{}", image.repository, image.tag),
+        "imagePullPolicy": image.pull_policy(),
+        "command": command
+    })]
+}
+
+fn build_migration_container(
+    obj: &crd::AuthentikSpec,
+    conn: &Connection,
+    image: &crd::AuthentikImage,
+) -> Value {
+    json!({
+        "name": "migrate",
+        "image": format!("{}:{}", image.repository, image.tag),
+        "imagePullPolicy": image.pull_policy(),
+        "args": ["migrate"],
+        "terminationMessagePolicy": obj.termination_message_policy,
+        "securityContext": build_security_context(obj),
+        "volumeMounts": build_volume_mounts(obj),
+        "env": build_env_server(obj, conn)
+    })
+}
+
+fn build_args(role: &str, extra_args: &[String]) -> Vec<String> {
+    let mut args = vec![role.to_string()];
+    args.extend(extra_args.iter().cloned());
+    args
+}
+
+pub fn build_security_context(obj: &crd::AuthentikSpec) -> Option<Value> {
+    if !obj.read_only_root_filesystem {
+        return None;
+    }
+
+    Some(json!({ "readOnlyRootFilesystem": true }))
+}
+
+pub fn build_volumes(obj: &crd::AuthentikSpec) -> Vec<Value> {
+    let mut volumes = Vec::new();
+
+    if let Some(media) = obj.media.as_ref() {
+        volumes.push(json!({
+            "name": "media",
+            "emptyDir": build_empty_dir(media)
+        }));
+    }
+
+    if obj.read_only_root_filesystem {
+        volumes.push(json!({
+            "name": "tmp",
+            "emptyDir": {}
+        }));
+    }
+
+    for volume in obj.extra_volumes.iter() {
+        volumes.push(json!(volume));
+    }
+
+    volumes
+}
+
+pub fn build_volume_mounts(obj: &crd::AuthentikSpec) -> Vec<Value> {
+    let mut mounts = Vec::new();
+
+    if obj.media.is_some() {
+        mounts.push(json!({
+            "name": "media",
+            "mountPath": "/media"
+        }));
+    }
+
+    if obj.read_only_root_filesystem {
+        mounts.push(json!({
+            "name": "tmp",
+            "mountPath": "/tmp"
+        }));
+    }
+
+    for mount in obj.extra_volume_mounts.iter() {
+        mounts.push(json!(mount));
+    }
+
+    mounts
+}
+
+fn build_empty_dir(empty_dir: &crd::AuthentikEmptyDir) -> Value {
+    json!({
+        "sizeLimit": empty_dir.size_limit,
+        "medium": empty_dir.medium,
     })
 }
 
-fn build_env(obj: &crd::AuthentikSpec) -> Vec<EnvVar> {
+fn build_lifecycle(pre_stop: Option<&Vec<String>>) -> Option<Value> {
+    pre_stop.map(|command| {
+        json!({
+            "preStop": {
+                "exec": {
+                    "command": command
+                }
+            }
+        })
+    })
+}
+
+// The environment shared by the server and the worker, with the connections and the secret key.
+fn build_env_base(obj: &crd::AuthentikSpec, conn: &Connection) -> Vec<EnvVar> {
     let mut env = vec![
         EnvVar {
             name: "AUTHENTIK_LOG_LEVEL".to_string(),
             value: Some(obj.log_level.clone()),
             value_from: None,
         },
-        EnvVar {
-            name: "AUTHENTIK_SECRET_KEY".to_string(),
-            value: obj.secret_key.clone(),
-            value_from: None,
-        },
-        EnvVar {
-            name: "AUTHENTIK_BOOTSTRAP_TOKEN".to_string(),
-            value: Some(TEMP_AUTH_TOKEN.to_string()),
-            value_from: None,
-        },
+        conn.secret_key.value.to_env("AUTHENTIK_SECRET_KEY"),
         EnvVar {
             name: "AUTHENTIK_FOOTER_LINKS".to_string(),
-            value: Some(serde_json::to_string(&obj.footer_links).expect("Invalid footer")),
+            value: Some(json!(obj.footer_links()).to_string()),
             value_from: None,
         },
         EnvVar {
             name: "AUTHENTIK_DISABLE_STARTUP_ANALYTICS".to_string(),
-            value: Some("true".to_string()),
+            value: Some((!obj.telemetry.startup_analytics).to_string()),
             value_from: None,
         },
         EnvVar {
             name: "AUTHENTIK_ERROR_REPORTING__ENABLED".to_string(),
-            value: Some("false".to_string()),
+            value: Some(obj.telemetry.error_reporting.to_string()),
             value_from: None,
         },
         EnvVar {
             name: "AUTHENTIK_POSTGRESQL__HOST".to_string(),
-            value: Some(obj.postgres.host.clone()),
+            value: Some(conn.postgres.host.clone()),
             value_from: None,
         },
         EnvVar {
             name: "AUTHENTIK_POSTGRESQL__PORT".to_string(),
-            value: Some(obj.postgres.port.to_string()),
+            value: Some(conn.postgres.port.to_string()),
             value_from: None,
         },
         EnvVar {
             name: "AUTHENTIK_POSTGRESQL__NAME".to_string(),
-            value: Some(obj.postgres.database.clone()),
+            value: Some(conn.postgres.database.clone()),
             value_from: None,
         },
-        EnvVar {
-            name: "AUTHENTIK_POSTGRESQL__USER".to_string(),
-            value: Some(obj.postgres.username.clone()),
+        conn.postgres.username.to_env("AUTHENTIK_POSTGRESQL__USER"),
+        conn.postgres
+            .password
+            .to_env("AUTHENTIK_POSTGRESQL__PASSWORD"),
+    ];
+
+    // The bootstrap token is only needed to create the operator token.
+    if obj.api_token_secret_ref.is_none() {
+        env.push(EnvVar {
+            name: "AUTHENTIK_BOOTSTRAP_TOKEN".to_string(),
+            value: Some(TEMP_AUTH_TOKEN.to_string()),
             value_from: None,
-        },
-        EnvVar {
-            name: "AUTHENTIK_REDIS__HOST".to_string(),
-            value: Some(obj.redis.host.clone()),
+        });
+    }
+
+    if let Some(listen) = obj.listen.as_ref() {
+        env.push(EnvVar {
+            name: "AUTHENTIK_LISTEN__METRICS".to_string(),
+            value: Some(format!("{}:{}", listen.address, listen.metrics_port)),
             value_from: None,
-        },
-        EnvVar {
-            name: "AUTHENTIK_REDIS__PORT".to_string(),
-            value: Some(obj.redis.port.to_string()),
+        });
+    }
+
+    if let Some(gdpr_compliance) = obj.gdpr_compliance {
+        env.push(EnvVar {
+            name: "AUTHENTIK_GDPR_COMPLIANCE".to_string(),
+            value: Some(gdpr_compliance.to_string()),
             value_from: None,
-        },
-    ];
+        });
+    }
 
-    if let Some((secret, key)) = obj
-        .postgres
-        .password_secret
-        .clone()
-        .zip(obj.postgres.password_secret_key.as_ref())
+    if let Some(impersonation) = obj.ui.as_ref().and_then(|ui| ui.impersonation) {
+        env.push(EnvVar {
+            name: "AUTHENTIK_IMPERSONATION".to_string(),
+            value: Some(impersonation.to_string()),
+            value_from: None,
+        });
+    }
+
+    if let Some(image_base) = obj
+        .outposts
+        .as_ref()
+        .and_then(|outposts| outposts.container_image_base.as_ref())
     {
         env.push(EnvVar {
-            name: "AUTHENTIK_POSTGRESQL__PASSWORD".to_string(),
-            value: None,
-            value_from: Some(EnvVarSource {
-                config_map_key_ref: None,
-                field_ref: None,
-                resource_field_ref: None,
-                secret_key_ref: Some(SecretKeySelector {
-                    key: key.clone(),
-                    name: Some(secret),
-                    optional: Some(false),
-                }),
-            }),
+            name: "AUTHENTIK_OUTPOSTS__CONTAINER_IMAGE_BASE".to_string(),
+            value: Some(image_base.clone()),
+            value_from: None,
+        });
+    }
+
+    env.extend(build_env_redis(&obj.redis, conn));
+
+    env.extend(build_env_cache(obj.cache.as_ref()));
+
+    env.extend(build_env_smtp(obj.smtp.as_ref()));
+
+    env
+}
+
+// The server gets the shared environment, plus the settings of the web server and the requests it serves.
+// This is also used by the other containers running the server image, such as the migrations.
+pub fn build_env_server(obj: &crd::AuthentikSpec, conn: &Connection) -> Vec<EnvVar> {
+    let mut env = build_env_base(obj, conn);
+
+    if let Some(listen) = obj.listen.as_ref() {
+        env.push(EnvVar {
+            name: "AUTHENTIK_LISTEN__HTTP".to_string(),
+            value: Some(format!("{}:{}", listen.address, listen.http_port)),
+            value_from: None,
         });
-    } else {
+    }
+
+    if let Some(proxy) = obj.proxy.as_ref() {
+        if !proxy.trusted_origins.is_empty() {
+            env.push(EnvVar {
+                name: "AUTHENTIK_CSRF__TRUSTED_ORIGINS".to_string(),
+                value: Some(proxy.trusted_origins.join(",")),
+                value_from: None,
+            });
+        }
+        if !proxy.trusted_proxy_cidrs.is_empty() {
+            env.push(EnvVar {
+                name: "AUTHENTIK_LISTEN__TRUSTED_PROXY_CIDRS".to_string(),
+                value: Some(proxy.trusted_proxy_cidrs.join(",")),
+                value_from: None,
+            });
+        }
+    }
+
+    env.extend(obj.extra_env.iter().cloned());
+    dedup_env(env)
+}
+
+// The worker gets the shared environment, plus the tuning of the background tasks.
+fn build_env_worker(obj: &crd::AuthentikSpec, conn: &Connection) -> Vec<EnvVar> {
+    let mut env = build_env_base(obj, conn);
+
+    if let Some(concurrency) = obj.worker.concurrency {
         env.push(EnvVar {
-            name: "AUTHENTIK_POSTGRESQL__PASSWORD".to_string(),
-            value: Some(obj.postgres.password.clone()),
+            name: "AUTHENTIK_WORKER__CONCURRENCY".to_string(),
+            value: Some(concurrency.to_string()),
             value_from: None,
         });
     }
 
-    if let Some(password) = obj.redis.password.as_ref() {
+    if let Some(max_tasks_per_child) = obj.worker.max_tasks_per_child {
         env.push(EnvVar {
-            name: "AUTHENTIK_REDIS__PASSWORD".to_string(),
-            value: Some(password.clone()),
+            name: "AUTHENTIK_WORKER__MAX_TASKS_PER_CHILD".to_string(),
+            value: Some(max_tasks_per_child.to_string()),
             value_from: None,
         });
     }
 
-    env.extend(build_env_smtp(obj.smtp.as_ref()));
+    env.extend(obj.extra_env.iter().cloned());
+    dedup_env(env)
+}
+
+// Kubernetes doesn't define which value wins when a name is repeated, so only the last value for each
+// name is kept. It takes the place of the first, so the order stays the same and patches don't churn.
+fn dedup_env(env: Vec<EnvVar>) -> Vec<EnvVar> {
+    let mut deduped: Vec<EnvVar> = Vec::with_capacity(env.len());
+    for var in env {
+        match deduped
+            .iter_mut()
+            .find(|existing| existing.name == var.name)
+        {
+            Some(existing) => *existing = var,
+            None => deduped.push(var),
+        }
+    }
+
+    deduped
+}
+
+fn build_env_redis(obj: &crd::AuthentikRedis, conn: &Connection) -> Vec<EnvVar> {
+    let mut env = Vec::new();
+
+    // The password has to come first, so it can be referenced in the url below.
+    if let Some(password) = conn.redis.password.as_ref() {
+        env.push(password.to_env("AUTHENTIK_REDIS__PASSWORD"));
+    }
+
+    match &conn.redis.endpoint {
+        RedisEndpoint::Standalone(host, port) => {
+            env.push(EnvVar {
+                name: "AUTHENTIK_REDIS__HOST".to_string(),
+                value: Some(host.clone()),
+                value_from: None,
+            });
+            env.push(EnvVar {
+                name: "AUTHENTIK_REDIS__PORT".to_string(),
+                value: Some(port.to_string()),
+                value_from: None,
+            });
+        }
+        RedisEndpoint::Sentinel(sentinel) => {
+            let auth = match conn.redis.password {
+                Some(_) => ":$(AUTHENTIK_REDIS__PASSWORD)@",
+                None => "",
+            };
+            let hosts: Vec<String> = sentinel
+                .hosts
+                .iter()
+                .map(|host| format!("{}:{}", host.host, host.port))
+                .collect();
+
+            env.push(EnvVar {
+                name: "AUTHENTIK_REDIS__URL".to_string(),
+                value: Some(format!(
+                    "redis+sentinel://{}{}/{}?mastername={}",
+                    auth,
+                    hosts.join(","),
+                    sentinel.database,
+                    sentinel.master_name
+                )),
+                value_from: None,
+            });
+        }
+    }
+
+    if let Some(databases) = obj.databases.as_ref() {
+        for (name, database) in [
+            ("AUTHENTIK_REDIS__CACHE_DB", databases.cache),
+            ("AUTHENTIK_REDIS__MESSAGE_QUEUE_DB", databases.message_queue),
+            ("AUTHENTIK_REDIS__WS_DB", databases.websockets),
+            (
+                "AUTHENTIK_REDIS__OUTPOST_SESSION_DB",
+                databases.outpost_sessions,
+            ),
+        ] {
+            if let Some(database) = database {
+                env.push(EnvVar {
+                    name: name.to_string(),
+                    value: Some(database.to_string()),
+                    value_from: None,
+                });
+            }
+        }
+    }
+
+    env
+}
+
+fn build_env_cache(obj: Option<&crd::AuthentikCache>) -> Vec<EnvVar> {
+    let obj = match obj {
+        Some(obj) => obj,
+        None => return vec![],
+    };
+
+    let values = [
+        ("AUTHENTIK_CACHE__URL", obj.url.clone()),
+        (
+            "AUTHENTIK_CACHE__TIMEOUT",
+            obj.timeout.map(|v| v.to_string()),
+        ),
+        (
+            "AUTHENTIK_CACHE__TIMEOUT_FLOWS",
+            obj.timeout_flows.map(|v| v.to_string()),
+        ),
+        (
+            "AUTHENTIK_CACHE__TIMEOUT_POLICIES",
+            obj.timeout_policies.map(|v| v.to_string()),
+        ),
+        (
+            "AUTHENTIK_CACHE__TIMEOUT_REPUTATION",
+            obj.timeout_reputation.map(|v| v.to_string()),
+        ),
+        (
+            "AUTHENTIK_RESULT_BACKEND__URL",
+            obj.result_backend_url.clone(),
+        ),
+    ];
+
+    let mut env = Vec::new();
+    for (name, value) in values {
+        if let Some(value) = value {
+            env.push(EnvVar {
+                name: name.to_string(),
+                value: Some(value),
+                value_from: None,
+            });
+        }
+    }
 
     env
 }
@@ -268,7 +843,7 @@ fn build_env_smtp(obj: Option<&crd::AuthentikSmtp>) -> Vec<EnvVar> {
         None => return vec![],
     };
 
-    vec![
+    let mut env = vec![
         EnvVar {
             name: "AUTHENTIK_EMAIL__HOST".to_string(),
             value: Some(obj.host.clone()),
@@ -294,20 +869,102 @@ fn build_env_smtp(obj: Option<&crd::AuthentikSmtp>) -> Vec<EnvVar> {
             value: Some(obj.password.clone()),
             value_from: None,
         },
-        EnvVar {
-            name: "AUTHENTIK_EMAIL__USE_TLS".to_string(),
-            value: Some(obj.use_tls.to_string()),
-            value_from: None,
-        },
-        EnvVar {
-            name: "AUTHENTIK_EMAIL__USE_SSL".to_string(),
-            value: Some(obj.use_ssl.to_string()),
-            value_from: None,
-        },
         EnvVar {
             name: "AUTHENTIK_EMAIL__TIMEOUT".to_string(),
             value: Some(obj.timeout.to_string()),
             value_from: None,
         },
-    ]
+    ];
+
+    // At most one of these is enabled, both are disabled by default.
+    let encryption = match obj.encryption() {
+        crd::AuthentikSmtpEncryption::None => None,
+        crd::AuthentikSmtpEncryption::StartTls => Some("AUTHENTIK_EMAIL__USE_TLS"),
+        crd::AuthentikSmtpEncryption::Ssl => Some("AUTHENTIK_EMAIL__USE_SSL"),
+    };
+    if let Some(name) = encryption {
+        env.push(EnvVar {
+            name: name.to_string(),
+            value: Some("true".to_string()),
+            value_from: None,
+        });
+    }
+
+    env
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::resources::authentik::connection::{Postgres, Redis, SecretKey, Value as ConnValue};
+
+    fn spec() -> crd::AuthentikSpec {
+        serde_json::from_value(json!({
+            "logLevel": "info",
+            "postgres": {},
+            "redis": {},
+            "extraEnv": [
+                { "name": "EXTRA", "value": "first" },
+                { "name": "AUTHENTIK_LOG_LEVEL", "value": "debug" },
+                { "name": "EXTRA", "value": "second" },
+            ],
+        }))
+        .expect("Failed to parse the spec")
+    }
+
+    fn conn() -> Connection {
+        Connection {
+            postgres: Postgres {
+                host: "postgres".to_string(),
+                port: 5432,
+                database: "authentik".to_string(),
+                username: ConnValue::Plain("authentik".to_string()),
+                password: ConnValue::Plain("password".to_string()),
+            },
+            redis: Redis {
+                endpoint: RedisEndpoint::Standalone("redis".to_string(), 6379),
+                password: None,
+            },
+            secret_key: SecretKey {
+                value: ConnValue::Plain("secret".to_string()),
+                hash: String::new(),
+            },
+        }
+    }
+
+    fn assert_deduped(env: &[EnvVar]) {
+        let names: HashSet<&String> = env.iter().map(|var| &var.name).collect();
+        assert_eq!(names.len(), env.len(), "duplicate names in {:?}", env);
+
+        // The override takes the place of the variable of the operator, which comes first.
+        assert_eq!(env[0].name, "AUTHENTIK_LOG_LEVEL");
+        assert_eq!(env[0].value.as_deref(), Some("debug"));
+
+        let extra: Vec<&EnvVar> = env.iter().filter(|var| var.name == "EXTRA").collect();
+        assert_eq!(extra.len(), 1);
+        assert_eq!(extra[0].value.as_deref(), Some("second"));
+    }
+
+    #[test]
+    fn server_env_has_unique_names() {
+        assert_deduped(&build_env_server(&spec(), &conn()));
+    }
+
+    #[test]
+    fn worker_env_has_unique_names() {
+        assert_deduped(&build_env_worker(&spec(), &conn()));
+    }
+
+    #[test]
+    fn dedup_keeps_position_of_first() {
+        let var = |name: &str, value: &str| EnvVar {
+            name: name.to_string(),
+            value: Some(value.to_string()),
+            value_from: None,
+        };
+        let env = dedup_env(vec![var("A", "1"), var("B", "2"), var("A", "3")]);
+        assert_eq!(env, vec![var("A", "3"), var("B", "2")]);
+    }
 }
@@ -0,0 +1,127 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::batch::v1::Job;
+use kube::{
+    api::{DeleteParams, Patch, PatchParams, PostParams},
+    Api, Client, ResourceExt,
+};
+use serde_json::{json, Value};
+
+use super::{
+    connection::{self, Connection},
+    crd, deployment, labels,
+};
+
+static DIAGNOSE_ANNOTATION: &str = "ak.dany.dev/diagnose";
+
+// Run the diagnostics command in a Job when the annotation is changed.
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+
+    let requested = match obj.annotations().get(DIAGNOSE_ANNOTATION) {
+        Some(requested) => requested,
+        None => return Ok(()),
+    };
+
+    // The handled value is kept in the status, as the Job is removed a day after it finished.
+    let handled = obj
+        .status
+        .as_ref()
+        .and_then(|status| status.diagnose.as_ref());
+    if handled == Some(requested) {
+        return Ok(());
+    }
+
+    // The Job is kept for its logs, and is only replaced when a new run is requested.
+    let name = format!("authentik-{}-diagnose", instance);
+    let api: Api<Job> = Api::namespaced(client.clone(), &ns);
+    if let Some(job) = api.get_opt(&name).await? {
+        if job.annotations().get(DIAGNOSE_ANNOTATION) != Some(requested) {
+            api.delete(&name, &DeleteParams::background()).await?;
+            // The new Job can only be created once the old one is gone, which triggers a reconcile.
+            return Ok(());
+        }
+    } else {
+        let conn = connection::resolve(&obj.spec, client.clone(), &ns).await?;
+        let job = serde_json::from_value(build(instance.clone(), obj, requested, &conn))?;
+        api.create(&PostParams::default(), &job).await?;
+
+        info!(
+            "Started the diagnostics of `{}`, the output is in the logs of Job `{}`.",
+            instance, name
+        );
+    }
+
+    let servers: Api<crd::Authentik> = Api::namespaced(client, &ns);
+    servers
+        .patch_status(
+            &instance,
+            &PatchParams::default(),
+            &Patch::Merge(json!({
+                "status": {
+                    "diagnose": requested
+                }
+            })),
+        )
+        .await?;
+
+    Ok(())
+}
+
+fn build(name: String, obj: &crd::Authentik, requested: &str, conn: &Connection) -> Value {
+    let image = obj.spec.server_image();
+
+    let mut job = json!({
+        "apiVersion": "batch/v1",
+        "kind": "Job",
+        "metadata": {
+            "name": format!("authentik-{}-diagnose", name.clone()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "diagnostics".to_string()),
+            "annotations": {
+                DIAGNOSE_ANNOTATION: requested
+            },
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "spec": {
+            "backoffLimit": 0,
+            // Keep the Job around for a day, so the output can still be read.
+            "ttlSecondsAfterFinished": 24 * 60 * 60,
+            "template": {
+                "metadata": {
+                    "labels": labels::get_labels(name.clone(), obj.spec.version(), "diagnostics".to_string()),
+                },
+                "spec": {
+                    "restartPolicy": "Never",
+                    "automountServiceAccountToken": false,
+                    "volumes": deployment::build_volumes(&obj.spec),
+                    "containers": [{
+                        "name": "diagnose",
+                        "image": format!("{}:{}", image.repository, image.tag),
+                        "imagePullPolicy": image.pull_policy(),
+                        "command": obj.spec.diagnostics_command,
+                        "terminationMessagePolicy": obj.spec.termination_message_policy,
+                        "securityContext": deployment::build_security_context(&obj.spec),
+                        "volumeMounts": deployment::build_volume_mounts(&obj.spec),
+                        "env": deployment::build_env_server(&obj.spec, conn)
+                    }]
+                }
+            }
+        }
+    });
+
+    deployment::set_pod_options(&mut job["spec"]["template"]["spec"], &obj.spec);
+
+    job
+}
@@ -0,0 +1,121 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use kube::{runtime::reflector::ObjectRef, Resource, ResourceExt};
+use serde_json::json;
+
+use crate::resources::{hash_value, requeue_interval};
+
+use super::{crd, status};
+
+// The key of an instance, as its namespace and name.
+type Key = (String, String);
+
+// Tracks which instances are known to be in their desired state, so the reconciles triggered by
+// nothing but status updates can skip applying everything again.
+#[derive(Default)]
+pub struct Drift {
+    // The instances of which an owned object changed since their last full reconcile.
+    changed: Mutex<HashSet<Key>>,
+    // The time of the last successful full reconcile of each instance, and a hash of its metadata.
+    reconciled: Mutex<HashMap<Key, (Instant, String)>>,
+}
+
+impl Drift {
+    // Map changes of an owned object to its owners, marking these as changed along the way.
+    pub fn owners<K: Resource>(
+        self: &Arc<Self>,
+    ) -> impl Fn(K) -> Vec<ObjectRef<crd::Authentik>> + Send + Sync + 'static {
+        let drift = self.clone();
+        move |child| {
+            let ns = child.namespace();
+            let owners: Vec<ObjectRef<crd::Authentik>> = child
+                .owner_references()
+                .iter()
+                .filter_map(|owner| ObjectRef::from_owner_ref(ns.as_deref(), owner, ()))
+                .collect();
+
+            let mut changed = drift
+                .changed
+                .lock()
+                .expect("Failed to lock the changed instances");
+            for owner in owners.iter() {
+                changed.insert((
+                    owner.namespace.clone().unwrap_or_default(),
+                    owner.name.clone(),
+                ));
+            }
+
+            owners
+        }
+    }
+
+    // The time until the next full reconcile, if nothing changed since the last one.
+    pub fn unchanged_for(&self, obj: &crd::Authentik) -> Option<Duration> {
+        let observed = obj
+            .status
+            .as_ref()
+            .and_then(|status| status.observed_generation);
+        if observed.is_none() || observed != obj.metadata.generation {
+            return None;
+        }
+        if status::forced_reconcile(obj).is_some() {
+            return None;
+        }
+
+        let key = key(obj);
+        if self
+            .changed
+            .lock()
+            .expect("Failed to lock the changed instances")
+            .contains(&key)
+        {
+            return None;
+        }
+
+        // Annotations and labels don't change the generation, but some of these change the outcome.
+        let reconciled = self
+            .reconciled
+            .lock()
+            .expect("Failed to lock the reconciled instances");
+        let (at, metadata) = reconciled.get(&key)?;
+        if *metadata != hash_metadata(obj) {
+            return None;
+        }
+
+        // Past the interval this is the periodic requeue, which always does a full reconcile.
+        requeue_interval(obj).checked_sub(at.elapsed())
+    }
+
+    // Forget about earlier changes at the start of a full reconcile, as it applies everything anyway.
+    pub fn start(&self, obj: &crd::Authentik) {
+        let key = key(obj);
+        self.changed
+            .lock()
+            .expect("Failed to lock the changed instances")
+            .remove(&key);
+        self.reconciled
+            .lock()
+            .expect("Failed to lock the reconciled instances")
+            .remove(&key);
+    }
+
+    // Record a full reconcile that left the instance in its desired state.
+    pub fn finish(&self, obj: &crd::Authentik) {
+        self.reconciled
+            .lock()
+            .expect("Failed to lock the reconciled instances")
+            .insert(key(obj), (Instant::now(), hash_metadata(obj)));
+    }
+}
+
+fn key(obj: &crd::Authentik) -> Key {
+    (obj.namespace().unwrap_or_default(), obj.name_any())
+}
+
+fn hash_metadata(obj: &crd::Authentik) -> String {
+    hash_value(&json!([obj.labels(), obj.annotations()]))
+}
@@ -1,11 +1,10 @@
 use anyhow::{anyhow, Result};
 use k8s_openapi::api::networking::v1::Ingress;
-use kube::{
-    api::{DeleteParams, Patch, PatchParams},
-    Api, Client, ResourceExt,
-};
+use kube::{api::DeleteParams, Api, Client, ResourceExt};
 use serde_json::{json, Value};
 
+use crate::resources::apply_if_changed;
+
 use super::{crd, labels};
 
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
@@ -23,10 +22,10 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
 
     if let Some(ing) = &obj.spec.ingress {
         // Create or update the ingress.
-        api.patch(
+        apply_if_changed(
+            &api,
             &format!("authentik-{}", instance),
-            &PatchParams::apply("authentik.ak-operator").force(),
-            &Patch::Apply(build(instance.clone(), obj, ing)),
+            build(instance.clone(), obj, ing),
         )
         .await?;
     } else {
@@ -36,6 +35,23 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
         }
     }
 
+    let metrics_name = format!("authentik-{}-metrics", instance);
+    match &obj.spec.metrics_ingress {
+        Some(ing) => {
+            apply_if_changed(
+                &api,
+                &metrics_name,
+                build_metrics(instance.clone(), obj, ing),
+            )
+            .await?;
+        }
+        None => {
+            if api.get_opt(&metrics_name).await?.is_some() {
+                api.delete(&metrics_name, &DeleteParams::default()).await?;
+            }
+        }
+    }
+
     Ok(())
 }
 
@@ -44,6 +60,47 @@ pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
 }
 
 fn build(name: String, obj: &crd::Authentik, ing: &crd::AuthentikIngress) -> Value {
+    build_ingress(
+        name.clone(),
+        format!("authentik-{}", name),
+        "ingress",
+        "http",
+        obj,
+        ing,
+    )
+}
+
+// The metrics have no auth of their own, so these are protected with basic auth by the ingress
+// controller. The annotations are those of ingress-nginx.
+fn build_metrics(name: String, obj: &crd::Authentik, ing: &crd::AuthentikMetricsIngress) -> Value {
+    let mut value = build_ingress(
+        name.clone(),
+        format!("authentik-{}-metrics", name),
+        "metrics-ingress",
+        "metrics",
+        obj,
+        &crd::AuthentikIngress {
+            class_name: ing.class_name.clone(),
+            rules: ing.rules.clone(),
+            tls: ing.tls.clone(),
+        },
+    );
+    value["metadata"]["annotations"] = json!({
+        "nginx.ingress.kubernetes.io/auth-type": "basic",
+        "nginx.ingress.kubernetes.io/auth-secret": ing.basic_auth.secret_name,
+        "nginx.ingress.kubernetes.io/auth-realm": ing.basic_auth.realm,
+    });
+    value
+}
+
+fn build_ingress(
+    name: String,
+    ingress_name: String,
+    component: &str,
+    port: &str,
+    obj: &crd::Authentik,
+    ing: &crd::AuthentikIngress,
+) -> Value {
     let tls = ing
         .tls
         .iter()
@@ -69,7 +126,7 @@ fn build(name: String, obj: &crd::Authentik, ing: &crd::AuthentikIngress) -> Val
                             "service": {
                                 "name": format!("authentik-{}", name.clone()),
                                 "port": {
-                                    "name": "http"
+                                    "name": port
                                 }
                             }
                         }
@@ -88,8 +145,8 @@ fn build(name: String, obj: &crd::Authentik, ing: &crd::AuthentikIngress) -> Val
         "apiVersion": "networking.k8s.io/v1",
         "kind": "Ingress",
         "metadata": {
-            "name": format!("authentik-{}", name.clone()),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "ingress".to_string()),
+            "name": ingress_name,
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), component.to_string()),
             "ownerReferences": [{
                 "apiVersion": "ak.dany.dev/v1",
                 "kind": "Authentik",
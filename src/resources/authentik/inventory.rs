@@ -0,0 +1,151 @@
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::{
+    api::{
+        apps::v1::Deployment,
+        autoscaling::v2::HorizontalPodAutoscaler,
+        batch::v1::Job,
+        core::v1::{Secret, Service, ServiceAccount},
+        networking::v1::Ingress,
+        rbac::v1::{ClusterRole, ClusterRoleBinding},
+    },
+    NamespaceResourceScope,
+};
+use kube::{
+    api::{ListParams, Patch, PatchParams},
+    Api, Client, Resource, ResourceExt,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use crate::akapi::{
+    auth::{get_valid_token, operator_token_secret},
+    blueprint::FindBlueprint,
+    cache::ListCache,
+    managed_name_prefix,
+    user::{Find, FindBody},
+    AkApiRoute, AkClient, API_USER,
+};
+
+use super::crd;
+
+// List everything the operator manages for the instance in its status, including the objects in
+// Authentik that have no owner references to find them by.
+pub async fn reconcile(obj: &crd::Authentik, client: Client, cache: &ListCache) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+
+    let lp = ListParams::default().labels(&format!(
+        "app.kubernetes.io/created-by=authentik-operator,app.kubernetes.io/part-of=ak-ak,app.kubernetes.io/instance={}",
+        instance
+    ));
+    let mut kubernetes = Vec::new();
+    kubernetes.extend(namespaced::<Deployment>(client.clone(), &ns, &lp).await?);
+    kubernetes.extend(namespaced::<Service>(client.clone(), &ns, &lp).await?);
+    kubernetes.extend(namespaced::<Ingress>(client.clone(), &ns, &lp).await?);
+    kubernetes.extend(namespaced::<HorizontalPodAutoscaler>(client.clone(), &ns, &lp).await?);
+    kubernetes.extend(namespaced::<Job>(client.clone(), &ns, &lp).await?);
+    kubernetes.extend(namespaced::<ServiceAccount>(client.clone(), &ns, &lp).await?);
+
+    // The token secret can be in the central namespace, in which case that is included.
+    let (secret_ns, secret_name) = operator_token_secret(&ns, &instance);
+    let secrets: Api<Secret> = Api::namespaced(client.clone(), &secret_ns);
+    if secrets.get_opt(&secret_name).await?.is_some() {
+        match secret_ns == ns {
+            true => kubernetes.push(format!("Secret/{}", secret_name)),
+            false => kubernetes.push(format!("Secret/{}/{}", secret_ns, secret_name)),
+        }
+    }
+
+    // These are not namespaced, so these are looked up by name instead of by the labels.
+    let cluster_name = format!("ak-{}", instance);
+    let roles: Api<ClusterRole> = Api::all(client.clone());
+    if roles.get_opt(&cluster_name).await?.is_some() {
+        kubernetes.push(format!("ClusterRole/{}", cluster_name));
+    }
+    let bindings: Api<ClusterRoleBinding> = Api::all(client.clone());
+    if bindings.get_opt(&cluster_name).await?.is_some() {
+        kubernetes.push(format!("ClusterRoleBinding/{}", cluster_name));
+    }
+    kubernetes.sort();
+
+    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
+    let ak = AkClient::new(&api_key, &instance, &ns)?;
+    let prefix = managed_name_prefix(&instance);
+
+    let mut authentik = Vec::new();
+    for flow in cache.flows(&ak).await?.iter() {
+        if flow.slug.starts_with(&prefix) {
+            authentik.push(format!("flow/{}", flow.slug));
+        }
+    }
+    for stage in cache.stages(&ak).await?.iter() {
+        if stage.name.starts_with(&prefix) {
+            authentik.push(format!("stage/{}", stage.name));
+        }
+    }
+    for blueprint in FindBlueprint::send(&ak, ()).await? {
+        if blueprint.name.starts_with(&prefix) {
+            authentik.push(format!("blueprint/{}", blueprint.name));
+        }
+    }
+    // The account is only created by the operator when no token is provided.
+    if obj.spec.api_token_secret_ref.is_none() {
+        let users = Find::send(
+            &ak,
+            FindBody {
+                username: Some(API_USER.to_string()),
+                ..Default::default()
+            },
+        )
+        .await?;
+        if let Some(user) = users.into_iter().find(|user| user.username == API_USER) {
+            authentik.push(format!("user/{}", user.pk));
+        }
+    }
+    authentik.sort();
+
+    let managed = crd::AuthentikManagedResources {
+        kubernetes,
+        authentik,
+    };
+    if obj.status.as_ref().map(|status| &status.managed_resources) == Some(&managed) {
+        return Ok(());
+    }
+
+    let api: Api<crd::Authentik> = Api::namespaced(client, &ns);
+    api.patch_status(
+        &instance,
+        &PatchParams::default(),
+        &Patch::Merge(json!({
+            "status": {
+                "managedResources": managed
+            }
+        })),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn namespaced<K>(client: Client, ns: &str, lp: &ListParams) -> Result<Vec<String>>
+where
+    K: Resource<Scope = NamespaceResourceScope> + Clone + DeserializeOwned + Debug,
+    <K as Resource>::DynamicType: Default,
+{
+    let api: Api<K> = Api::namespaced(client, ns);
+    Ok(api
+        .list(lp)
+        .await?
+        .items
+        .iter()
+        .map(|obj| format!("{}/{}", K::kind(&Default::default()), obj.name_any()))
+        .collect())
+}
@@ -10,6 +10,10 @@ pub fn get_labels(
         "app.kubernetes.io/created-by".to_string(),
         "authentik-operator".to_string(),
     );
+    labels.insert(
+        "app.kubernetes.io/managed-by".to_string(),
+        "authentik-operator".to_string(),
+    );
     labels.insert("app.kubernetes.io/version".to_string(), version);
 
     labels
@@ -0,0 +1,96 @@
+use anyhow::{anyhow, Result};
+use futures::{stream, StreamExt};
+use kube::{Client, ResourceExt};
+
+use crate::akapi::{
+    auth::get_valid_token,
+    cache::ListCache,
+    flow::{DeleteFlow, DeleteFlowError},
+    managed_name_prefix,
+    stages::{DeleteStage, DeleteStageError},
+    AkApiRoute, AkClient,
+};
+
+use super::crd;
+
+static MAX_CONCURRENT_DELETES: usize = 8;
+
+pub async fn reconcile(_obj: &crd::Authentik, _client: Client, _cache: &ListCache) -> Result<()> {
+    Ok(())
+}
+
+pub async fn cleanup(obj: &crd::Authentik, client: Client, cache: &ListCache) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let prefix = managed_name_prefix(&instance);
+
+    // Get the API key.
+    let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
+    let ak = AkClient::new(&api_key, &instance, &ns)?;
+
+    // Delete the flows first, as these bind the stages.
+    match cache.flows(&ak).await {
+        Ok(flows) => {
+            let slugs = flows
+                .iter()
+                .map(|flow| flow.slug.clone())
+                .filter(|slug| slug.starts_with(&prefix));
+
+            stream::iter(slugs)
+                .for_each_concurrent(MAX_CONCURRENT_DELETES, |slug| {
+                    let ak = &ak;
+                    async move {
+                        match DeleteFlow::send(ak, slug.clone()).await {
+                            Ok(_) => info!("Deleted managed flow `{}`.", slug),
+                            Err(DeleteFlowError::NotFound) => {}
+                            Err(e) => warn!(
+                                "Failed to delete managed flow `{}`, ignoring during deletion. ({})",
+                                slug, e
+                            ),
+                        }
+                    }
+                })
+                .await;
+            cache.invalidate_flows().await;
+        }
+        Err(_) => {
+            warn!("Failed to get the flows, skipping deleting the managed flows.");
+        }
+    };
+
+    match cache.stages(&ak).await {
+        Ok(stages) => {
+            let stages = stages
+                .iter()
+                .filter(|stage| stage.name.starts_with(&prefix));
+
+            stream::iter(stages)
+                .for_each_concurrent(MAX_CONCURRENT_DELETES, |stage| {
+                    let ak = &ak;
+                    async move {
+                        match DeleteStage::send(ak, stage.pk.clone()).await {
+                            Ok(_) => info!("Deleted managed stage `{}`.", stage.name),
+                            Err(DeleteStageError::NotFound) => {}
+                            Err(e) => warn!(
+                                "Failed to delete managed stage `{}`, ignoring during deletion. ({})",
+                                stage.name, e
+                            ),
+                        }
+                    }
+                })
+                .await;
+            cache.invalidate_stages().await;
+        }
+        Err(_) => {
+            warn!("Failed to get the stages, skipping deleting the managed stages.");
+        }
+    };
+
+    Ok(())
+}
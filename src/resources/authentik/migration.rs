@@ -0,0 +1,211 @@
+use anyhow::{anyhow, bail, Result};
+use k8s_openapi::api::apps::v1::Deployment;
+use kube::{Api, Client, ResourceExt};
+
+use crate::akapi::{admin::GetVersion, auth::get_valid_token, AkApiRoute, AkClient};
+
+use super::{crd, status::Readiness};
+
+static ALLOW_DOWNGRADE_ANNOTATION: &str = "ak.dany.dev/allow-downgrade";
+pub static ROLLOUT_MESSAGE: &str = "Waiting for the new server pods to become available.";
+
+// Authentik migrates the database before the API is served, so the instance is only ready once the
+// new server pods are rolled out and the API reports the expected version.
+pub async fn check(obj: &crd::Authentik, client: Client) -> Result<Readiness> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+
+    // Wait for the rollout of the server, the old pods still serve the old version.
+    if !server_rolled_out(obj, client.clone()).await? {
+        return Ok(Readiness::Migrating(ROLLOUT_MESSAGE.to_string()));
+    }
+
+    let api_key = match get_valid_token(client, &ns, &instance).await {
+        Ok(api_key) => api_key,
+        Err(_) => {
+            return Ok(Readiness::Migrating(
+                "Waiting for the API to become available.".to_string(),
+            ))
+        }
+    };
+    let ak = AkClient::new(&api_key, &instance, &ns)?;
+
+    let version = match GetVersion::send(&ak, ()).await {
+        Ok(version) => version.version_current,
+        Err(e) => {
+            return Ok(Readiness::Migrating(format!(
+                "Waiting for the API to become available ({}).",
+                e
+            )))
+        }
+    };
+
+    // Only tags that are versions can be compared, others such as `latest` are trusted.
+    let expected = obj.spec.version();
+    let is_version = expected.starts_with(|c: char| c.is_ascii_digit());
+    if is_version && version != expected && !version.starts_with(&format!("{}.", expected)) {
+        return Ok(Readiness::Migrating(format!(
+            "Running version `{}`, waiting for version `{}`.",
+            version, expected
+        )));
+    }
+
+    Ok(Readiness::Ready(version))
+}
+
+// Refuse to roll out an older version than the one that last ran, as the migrations of the
+// database cannot be undone. Tags that aren't versions, such as `latest`, are not checked.
+pub fn check_downgrade(obj: &crd::Authentik) -> Result<()> {
+    if obj.annotations().contains_key(ALLOW_DOWNGRADE_ANNOTATION) {
+        return Ok(());
+    }
+
+    let running = match obj
+        .status
+        .as_ref()
+        .and_then(|status| status.version.as_ref())
+    {
+        Some(running) => running,
+        None => return Ok(()),
+    };
+    let expected = obj.spec.version();
+    let (running_parts, expected_parts) = match (parse_version(running), parse_version(&expected)) {
+        (Some(running), Some(expected)) => (running, expected),
+        _ => return Ok(()),
+    };
+
+    // A tag like `2023.10` covers all its patch releases, so only the parts both have are compared.
+    let len = running_parts.len().min(expected_parts.len());
+    if expected_parts[..len] < running_parts[..len] {
+        bail!(
+            "Refusing to downgrade from version `{}` to `{}`, as the database may already be migrated. Set the `{}` annotation to do this anyway.",
+            running,
+            expected,
+            ALLOW_DOWNGRADE_ANNOTATION
+        );
+    }
+
+    Ok(())
+}
+
+// The numeric parts of a version, such as `2023.10.4`. Anything after these, like `-rc1`, is ignored.
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    let version = version.strip_prefix('v').unwrap_or(version);
+    let parts: Vec<u64> = version
+        .split('.')
+        .map_while(|part| {
+            let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+            digits.parse().ok()
+        })
+        .collect();
+
+    match parts.is_empty() {
+        true => None,
+        false => Some(parts),
+    }
+}
+
+// Whether all pods of the server run the current pod template and are available.
+pub async fn server_rolled_out(obj: &crd::Authentik, client: Client) -> Result<bool> {
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+    let api: Api<Deployment> = Api::namespaced(client, &ns);
+    let deployment = api
+        .get(&format!("authentik-{}-server", obj.name_any()))
+        .await?;
+    Ok(rolled_out(&deployment))
+}
+
+fn rolled_out(deployment: &Deployment) -> bool {
+    let replicas = deployment
+        .spec
+        .as_ref()
+        .and_then(|spec| spec.replicas)
+        .unwrap_or(1);
+    let status = match deployment.status.as_ref() {
+        Some(status) => status,
+        None => return false,
+    };
+
+    status.observed_generation >= deployment.metadata.generation
+        && status.updated_replicas.unwrap_or(0) >= replicas
+        && status.replicas.unwrap_or(0) <= status.updated_replicas.unwrap_or(0)
+        && status.available_replicas.unwrap_or(0) >= replicas
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn authentik(tag: &str, running: Option<&str>, allow_downgrade: bool) -> crd::Authentik {
+        let mut annotations = json!({});
+        if allow_downgrade {
+            annotations[ALLOW_DOWNGRADE_ANNOTATION] = json!("true");
+        }
+        serde_json::from_value(json!({
+            "apiVersion": "ak.dany.dev/v1",
+            "kind": "Authentik",
+            "metadata": { "name": "authentik", "annotations": annotations },
+            "spec": {
+                "image": { "tag": tag },
+                "postgres": {},
+                "redis": {},
+            },
+            "status": { "version": running },
+        }))
+        .expect("Failed to parse the object")
+    }
+
+    #[test]
+    fn parse_version_takes_the_numeric_parts() {
+        assert_eq!(parse_version("2023.10.4"), Some(vec![2023, 10, 4]));
+        assert_eq!(parse_version("v2023.10.4"), Some(vec![2023, 10, 4]));
+        assert_eq!(parse_version("2023.10.0-rc1"), Some(vec![2023, 10, 0]));
+        assert_eq!(parse_version("2023.10"), Some(vec![2023, 10]));
+        assert_eq!(parse_version("latest"), None);
+        assert_eq!(parse_version(""), None);
+    }
+
+    #[test]
+    fn downgrade_is_refused() {
+        assert!(check_downgrade(&authentik("2023.8.3", Some("2023.10.4"), false)).is_err());
+        assert!(check_downgrade(&authentik("2023.10.3", Some("2023.10.4"), false)).is_err());
+        assert!(check_downgrade(&authentik("v2023.8.3", Some("2023.10.4"), false)).is_err());
+    }
+
+    #[test]
+    fn upgrade_and_same_version_are_allowed() {
+        assert!(check_downgrade(&authentik("2023.10.4", Some("2023.10.4"), false)).is_ok());
+        assert!(check_downgrade(&authentik("2023.10.5", Some("2023.10.4"), false)).is_ok());
+        assert!(check_downgrade(&authentik("2024.2.1", Some("2023.10.4"), false)).is_ok());
+        assert!(check_downgrade(&authentik("2023.10.4-rc1", Some("2023.10.4"), false)).is_ok());
+    }
+
+    #[test]
+    fn partial_tags_cover_their_patch_releases() {
+        assert!(check_downgrade(&authentik("2023.10", Some("2023.10.4"), false)).is_ok());
+        assert!(check_downgrade(&authentik("2023.8", Some("2023.10.4"), false)).is_err());
+        assert!(check_downgrade(&authentik("2023.10.4", Some("2023.10"), false)).is_ok());
+    }
+
+    #[test]
+    fn unknown_versions_are_not_checked() {
+        assert!(check_downgrade(&authentik("latest", Some("2023.10.4"), false)).is_ok());
+        assert!(check_downgrade(&authentik("2023.8.3", Some("latest"), false)).is_ok());
+        assert!(check_downgrade(&authentik("2023.8.3", None, false)).is_ok());
+    }
+
+    #[test]
+    fn annotation_allows_downgrade() {
+        assert!(check_downgrade(&authentik("2023.8.3", Some("2023.10.4"), true)).is_ok());
+    }
+}
@@ -4,12 +4,14 @@ use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use k8s_openapi::api::{
     apps::v1::Deployment,
+    autoscaling::v2::HorizontalPodAutoscaler,
+    batch::v1::Job,
     core::v1::{Secret, Service, ServiceAccount},
     networking::v1::Ingress,
     rbac::v1::{ClusterRole, ClusterRoleBinding},
 };
 use kube::{
-    api::{Api, ListParams, ResourceExt},
+    api::{Api, ResourceExt},
     runtime::{self, controller::Action, finalizer},
     Client,
 };
@@ -19,44 +21,68 @@ mod controller;
 pub mod crd;
 pub mod labels;
 
+mod autoscaler;
+mod blueprints;
 mod clusteraccount;
+mod connection;
 mod deployment;
+mod diagnostics;
+mod drift;
 mod ingress;
+mod inventory;
+mod managedobjects;
+mod migration;
+mod orphan;
 mod secret;
 mod service;
 mod serviceaccount;
 mod servicegroup;
+mod smtp;
+mod status;
+mod tokens;
 
 use controller::Controller;
 
 use crate::ReconcileError;
 
-use super::list_lp;
+use super::{list_lp, reconcile_with_timeout, watched_api, watched_lp};
+
+// The amount of reconciles of a single object in the once mode, in case it keeps changing.
+static ONCE_MAX_ATTEMPTS: usize = 5;
+// The time between the reconciles of an instance that is migrating in the once mode.
+static ONCE_MIGRATING_DELAY: Duration = Duration::from_secs(10);
 
 pub struct Manager;
 
 impl Manager {
     pub fn new(client: Client) -> BoxFuture<'static, ()> {
         let ctrlr = Controller::new(client.clone());
+        let drift = ctrlr.drift();
 
-        let servers = Api::<crd::Authentik>::all(client.clone());
-        let deploys = Api::<Deployment>::all(client.clone());
-        let services = Api::<Service>::all(client.clone());
-        let ingresses = Api::<Ingress>::all(client.clone());
-        let secrets = Api::<Secret>::all(client.clone());
-        let serviceaccounts = Api::<ServiceAccount>::all(client.clone());
+        let servers = watched_api::<crd::Authentik>(client.clone());
+        let deploys = watched_api::<Deployment>(client.clone());
+        let services = watched_api::<Service>(client.clone());
+        let ingresses = watched_api::<Ingress>(client.clone());
+        let autoscalers = watched_api::<HorizontalPodAutoscaler>(client.clone());
+        let secrets = watched_api::<Secret>(client.clone());
+        let jobs = watched_api::<Job>(client.clone());
+        let serviceaccounts = watched_api::<ServiceAccount>(client.clone());
         let clusterroles = Api::<ClusterRole>::all(client.clone());
         let clusterrolebindings = Api::<ClusterRoleBinding>::all(client.clone());
         let lp = list_lp("ak-ak");
 
-        let drainer = runtime::Controller::new(servers, ListParams::default())
-            .owns(deploys, lp.clone())
-            .owns(services, lp.clone())
-            .owns(ingresses, lp.clone())
-            .owns(secrets, lp.clone())
-            .owns(serviceaccounts, lp.clone())
-            .owns(clusterroles, lp.clone())
-            .owns(clusterrolebindings, lp.clone())
+        // The owned objects are watched through the drift tracker, so changes of these always lead to
+        // a full reconcile of their owner.
+        let drainer = runtime::Controller::new(servers, watched_lp())
+            .watches(deploys, lp.clone(), drift.owners())
+            .watches(services, lp.clone(), drift.owners())
+            .watches(ingresses, lp.clone(), drift.owners())
+            .watches(autoscalers, lp.clone(), drift.owners())
+            .watches(secrets, lp.clone(), drift.owners())
+            .watches(jobs, lp.clone(), drift.owners())
+            .watches(serviceaccounts, lp.clone(), drift.owners())
+            .watches(clusterroles, lp.clone(), drift.owners())
+            .watches(clusterrolebindings, lp.clone(), drift.owners())
             .run(
                 move |obj, controller| Self::reconcile(obj, controller, client.clone()),
                 move |_, e, _| Self::error_policy(e),
@@ -69,6 +95,65 @@ impl Manager {
         drainer
     }
 
+    // Reconcile every object once instead of watching these, returning the objects that failed.
+    pub async fn reconcile_once(client: Client) -> Result<Vec<String>, kube::Error> {
+        let controller = Arc::new(Mutex::new(Controller::new(client.clone())));
+        let servers = watched_api::<crd::Authentik>(client.clone());
+        let mut failed = Vec::new();
+
+        for obj in servers.list(&watched_lp()).await? {
+            let ns = obj.namespace().unwrap_or_default();
+            let name = obj.name_any();
+            let api: Api<crd::Authentik> = Api::namespaced(client.clone(), &ns);
+
+            // The finalizer and the generated values are each added in a reconcile of their own, so
+            // keep going until a reconcile no longer changes the object. An instance that is still
+            // migrating isn't done either, so it is given some time to finish.
+            let mut obj = obj;
+            let mut done = false;
+            for _ in 0..ONCE_MAX_ATTEMPTS {
+                let version = obj.resource_version();
+                let obj_ref = Arc::new(obj.clone());
+                let result = Self::reconcile(obj_ref, controller.clone(), client.clone()).await;
+                if let Err(e) = result {
+                    error!(
+                        "Failed to reconcile Authentik `{}` in `{}`: {}",
+                        name, ns, e
+                    );
+                    failed.push(format!("{}/{}", ns, name));
+                    done = true;
+                    break;
+                }
+
+                match api.get_opt(&name).await? {
+                    Some(updated) if updated.resource_version() != version => obj = updated,
+                    Some(updated) if is_migrating(&updated) => {
+                        obj = updated;
+                        tokio::time::sleep(ONCE_MIGRATING_DELAY).await;
+                    }
+                    _ => {
+                        info!("Reconciled Authentik `{}` in `{}`.", name, ns);
+                        done = true;
+                        break;
+                    }
+                }
+            }
+
+            if !done {
+                error!(
+                    "Authentik `{}` in `{}` did not converge after {} attempts.",
+                    name, ns, ONCE_MAX_ATTEMPTS
+                );
+                failed.push(format!(
+                    "{}/{} (did not converge after {} attempts)",
+                    ns, name, ONCE_MAX_ATTEMPTS
+                ));
+            }
+        }
+
+        Ok(failed)
+    }
+
     async fn reconcile(
         obj: Arc<crd::Authentik>,
         controller: Arc<Mutex<Controller>>,
@@ -84,8 +169,12 @@ impl Manager {
             let controller = controller.lock().await;
 
             match event {
-                finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
+                finalizer::Event::Apply(server) => {
+                    reconcile_with_timeout(server.clone(), controller.reconcile(server)).await
+                }
+                finalizer::Event::Cleanup(server) => {
+                    reconcile_with_timeout(server.clone(), controller.cleanup(server)).await
+                }
             }
             .map_err(|e| e.into())
         })
@@ -98,3 +187,13 @@ impl Manager {
         Action::requeue(Duration::from_secs(60))
     }
 }
+
+// Whether the last recorded reconcile found the instance still migrating.
+fn is_migrating(obj: &crd::Authentik) -> bool {
+    obj.status.as_ref().is_some_and(|status| {
+        status
+            .conditions
+            .iter()
+            .any(|condition| condition.condition_type == "Ready" && condition.reason == "Migrating")
+    })
+}
@@ -0,0 +1,85 @@
+use std::fmt::Debug;
+
+use anyhow::{anyhow, Result};
+use k8s_openapi::{
+    api::{
+        apps::v1::Deployment,
+        autoscaling::v2::HorizontalPodAutoscaler,
+        core::v1::{Secret, Service, ServiceAccount},
+        networking::v1::Ingress,
+    },
+    NamespaceResourceScope,
+};
+use kube::{
+    api::{ListParams, Patch, PatchParams},
+    Api, Client, Resource, ResourceExt,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+
+use super::crd;
+
+// Remove the owner references to the instance, so the objects are kept when it is deleted.
+pub async fn release(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let uid = obj.uid().ok_or(anyhow!("Missing UID of `{}`.", instance))?;
+    let lp = ListParams::default().labels(&format!(
+        "app.kubernetes.io/part-of=ak-ak,app.kubernetes.io/instance={}",
+        instance
+    ));
+
+    release_all::<Deployment>(client.clone(), &ns, &lp, &uid).await?;
+    release_all::<Service>(client.clone(), &ns, &lp, &uid).await?;
+    release_all::<Ingress>(client.clone(), &ns, &lp, &uid).await?;
+    release_all::<HorizontalPodAutoscaler>(client.clone(), &ns, &lp, &uid).await?;
+    release_all::<Secret>(client.clone(), &ns, &lp, &uid).await?;
+    release_all::<ServiceAccount>(client, &ns, &lp, &uid).await?;
+
+    Ok(())
+}
+
+async fn release_all<K>(client: Client, ns: &str, lp: &ListParams, uid: &str) -> Result<()>
+where
+    K: Resource<Scope = NamespaceResourceScope> + Clone + DeserializeOwned + Debug,
+    <K as Resource>::DynamicType: Default,
+{
+    let api: Api<K> = Api::namespaced(client, ns);
+
+    for item in api.list(lp).await? {
+        let owners = item.owner_references();
+        if !owners.iter().any(|owner| owner.uid == uid) {
+            continue;
+        }
+
+        let remaining: Vec<_> = owners
+            .iter()
+            .filter(|owner| owner.uid != uid)
+            .cloned()
+            .collect();
+        api.patch(
+            &item.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(json!({
+                "metadata": {
+                    "ownerReferences": remaining
+                }
+            })),
+        )
+        .await?;
+
+        info!(
+            "Orphaned {} `{}`, it is kept after the deletion.",
+            K::kind(&Default::default()),
+            item.name_any()
+        );
+    }
+
+    Ok(())
+}
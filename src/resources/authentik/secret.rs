@@ -1,20 +1,28 @@
+use std::collections::BTreeMap;
+
 use anyhow::{anyhow, Result};
 use base64::encode;
+use chrono::{DateTime, Duration, Utc};
 use k8s_openapi::api::core::v1::Secret;
 use kube::{
-    api::{Patch, PatchParams},
+    api::{DeleteParams, ListParams, Patch, PatchParams},
     Api, Client, ResourceExt,
 };
+use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde_json::{json, Value};
 
 use crate::akapi::{
-    auth::{get_valid_secret_token, get_valid_token},
-    token::ViewToken,
+    auth::{get_valid_secret_token, get_valid_token, operator_token_secret},
+    token::{SetTokenKey, SetTokenKeyBody, ViewToken},
     token_identifier_name, AkApiRoute, AkClient,
 };
 
 use super::{crd, labels};
 
+static ROTATE_TOKEN_ANNOTATION: &str = "ak.dany.dev/rotate-token";
+static ROTATED_AT_ANNOTATION: &str = "ak.dany.dev/rotated-at";
+static NAMESPACE_LABEL: &str = "ak.dany.dev/namespace";
+
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let instance = obj
         .metadata
@@ -24,13 +32,61 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let ns = obj
         .namespace()
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let (secret_ns, name) = operator_token_secret(&ns, &instance);
+    let api: Api<Secret> = Api::namespaced(client.clone(), &secret_ns);
 
     // Check if the current secret stored is valid.
     // This is to surpress the logs in Authentik.
-    if get_valid_secret_token(client.clone(), &ns, &instance)
-        .await?
-        .is_some()
-    {
+    // The token may still be in the secret under its old name, the secret is then created below.
+    let stored = api.get_opt(&name).await?;
+    let token = get_valid_secret_token(client.clone(), &ns, &instance).await?;
+    if let (Some(token), Some(secret)) = (token, stored) {
+        if !rotation_due(obj, &secret) {
+            // Keep the extra metadata up to date, without touching the token.
+            let extra = &obj.spec.generated_metadata;
+            if !extra.labels.is_empty() || !extra.annotations.is_empty() {
+                api.patch(
+                    &name,
+                    &PatchParams::default(),
+                    &Patch::Merge(json!({
+                        "metadata": {
+                            "labels": build_labels(instance.clone(), &ns, obj),
+                            "annotations": extra.annotations,
+                        }
+                    })),
+                )
+                .await?;
+            }
+
+            return Ok(());
+        }
+
+        // Replace the key of the token, this invalidates the old key immediately.
+        let ak = AkClient::new(&token, &instance, &ns)?;
+        let key: String = thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(60)
+            .map(char::from)
+            .collect();
+
+        SetTokenKey::send(
+            &ak,
+            SetTokenKeyBody {
+                identifier: token_identifier_name(&instance, "operatortoken"),
+                key: key.clone(),
+            },
+        )
+        .await?;
+
+        // Store the new key right away, any later API call reads it from the secret.
+        api.patch(
+            &name,
+            &PatchParams::apply("authentik.ak-operator").force(),
+            &Patch::Apply(build(instance.clone(), &ns, obj, key)),
+        )
+        .await?;
+
+        info!("Rotated the operator token of `{}`.", instance);
         return Ok(());
     }
 
@@ -42,41 +98,120 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let token = ViewToken::send(&ak, token_identifier_name(&instance, "operatortoken")).await?;
 
     // Create or patch the secret.
-    let api: Api<Secret> = Api::namespaced(client, &ns);
-    let name = format!("ak-{}-api-operatortoken", instance);
     api.patch(
         &name,
         &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build(instance.clone(), obj, token)),
+        &Patch::Apply(build(instance.clone(), &ns, obj, token)),
     )
     .await?;
 
     Ok(())
 }
 
-pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
-    // Note: The secret will automatically be cleaned up by Kubernetes.
+pub async fn cleanup(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+
+    // Note: The secret will automatically be cleaned up by Kubernetes when it's in the same namespace.
+    let (secret_ns, _) = operator_token_secret(&ns, &instance);
+    if secret_ns == ns {
+        return Ok(());
+    }
+
+    // Owner references cannot cross namespaces, so find the secret by its labels instead.
+    let api: Api<Secret> = Api::namespaced(client, &secret_ns);
+    let lp = ListParams::default().labels(&format!(
+        "app.kubernetes.io/instance={},app.kubernetes.io/component=secret,{}={}",
+        instance, NAMESPACE_LABEL, ns
+    ));
+    api.delete_collection(&DeleteParams::default(), &lp).await?;
+
     Ok(())
 }
 
-fn build(name: String, obj: &crd::Authentik, token: String) -> Value {
-    json!({
+fn rotation_due(obj: &crd::Authentik, secret: &Secret) -> bool {
+    let annotations = secret.annotations();
+
+    // Rotate on demand when the annotation on the Authentik object has changed.
+    if let Some(requested) = obj.annotations().get(ROTATE_TOKEN_ANNOTATION) {
+        if annotations.get(ROTATE_TOKEN_ANNOTATION) != Some(requested) {
+            return true;
+        }
+    }
+
+    // Rotate when the configured interval has passed.
+    let days = match obj.spec.token_rotation_days {
+        Some(days) => days,
+        None => return false,
+    };
+
+    match annotations
+        .get(ROTATED_AT_ANNOTATION)
+        .and_then(|rotated_at| DateTime::parse_from_rfc3339(rotated_at).ok())
+    {
+        Some(rotated_at) => rotated_at + Duration::days(days.into()) <= Utc::now(),
+        None => true,
+    }
+}
+
+fn build(name: String, ns: &str, obj: &crd::Authentik, token: String) -> Value {
+    // The extra annotations go first, so they cannot override the annotations used by the operator.
+    let mut annotations = obj.spec.generated_metadata.annotations.clone();
+    annotations.insert(ROTATED_AT_ANNOTATION.to_string(), Utc::now().to_rfc3339());
+    if let Some(requested) = obj.annotations().get(ROTATE_TOKEN_ANNOTATION) {
+        annotations.insert(ROTATE_TOKEN_ANNOTATION.to_string(), requested.clone());
+    }
+
+    let (secret_ns, secret_name) = operator_token_secret(ns, &name);
+
+    let mut secret = json!({
         "apiVersion": "v1",
         "kind": "Secret",
         "type": "Opaque",
         "metadata": {
-            "name": format!("ak-{}-api-operatortoken", &name),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "secret".to_string()),
-            "ownerReferences": [{
-                "apiVersion": "ak.dany.dev/v1",
-                "kind": "Authentik",
-                "name": name,
-                "uid": obj.uid().expect("Failed to get UID of Authentik."),
-                "controller": true,
-            }]
+            "name": secret_name,
+            "labels": build_labels(name.clone(), ns, obj),
+            "annotations": annotations,
         },
         "data": {
             "token": encode(token)
         }
-    })
+    });
+
+    // Owner references are only valid within the same namespace.
+    if secret_ns == ns {
+        secret["metadata"]["ownerReferences"] = json!([{
+            "apiVersion": "ak.dany.dev/v1",
+            "kind": "Authentik",
+            "name": name,
+            "uid": obj.uid().expect("Failed to get UID of Authentik."),
+            "controller": true,
+        }]);
+    }
+
+    secret
+}
+
+fn build_labels(name: String, ns: &str, obj: &crd::Authentik) -> BTreeMap<String, String> {
+    // The extra labels go first, so they cannot override the labels used by the operator.
+    let mut labels = obj.spec.generated_metadata.labels.clone();
+    labels.extend(labels::get_labels(
+        name.clone(),
+        obj.spec.version(),
+        "secret".to_string(),
+    ));
+
+    // Owner references cannot be used outside the namespace, so the secret is found by label instead.
+    let (secret_ns, _) = operator_token_secret(ns, &name);
+    if secret_ns != ns {
+        labels.insert(NAMESPACE_LABEL.to_string(), ns.to_string());
+    }
+
+    labels
 }
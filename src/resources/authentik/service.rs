@@ -1,11 +1,10 @@
 use anyhow::{anyhow, Result};
 use k8s_openapi::api::core::v1::Service;
-use kube::{
-    api::{Patch, PatchParams},
-    Api, Client, ResourceExt,
-};
+use kube::{Api, Client, ResourceExt};
 use serde_json::{json, Value};
 
+use crate::resources::apply_if_changed;
+
 use super::{crd, labels};
 
 pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
@@ -19,10 +18,10 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
         .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
 
     let api: Api<Service> = Api::namespaced(client, &ns);
-    api.patch(
+    apply_if_changed(
+        &api,
         &format!("authentik-{}", instance),
-        &PatchParams::apply("authentik.ak-operator").force(),
-        &Patch::Apply(build(instance.clone(), obj)),
+        build(instance.clone(), obj),
     )
     .await?;
 
@@ -34,12 +33,28 @@ pub async fn cleanup(_obj: &crd::Authentik, _client: Client) -> Result<()> {
 }
 
 fn build(name: String, obj: &crd::Authentik) -> Value {
+    let mut ports = vec![json!({
+        "name": "http",
+        "port": 80,
+        "targetPort": "http",
+        "protocol": "TCP"
+    })];
+    // Only exposed for the metrics ingress, the validation ensures the pods have this port then.
+    if let (Some(_), Some(listen)) = (&obj.spec.metrics_ingress, &obj.spec.listen) {
+        ports.push(json!({
+            "name": "metrics",
+            "port": listen.metrics_port,
+            "targetPort": "metrics",
+            "protocol": "TCP"
+        }));
+    }
+
     json!({
         "apiVersion": "v1",
         "kind": "Service",
         "metadata": {
             "name": format!("authentik-{}", name.clone()),
-            "labels": labels::get_labels(name.clone(), obj.spec.image.tag.to_string(), "service".to_string()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "service".to_string()),
             "ownerReferences": [{
                 "apiVersion": "ak.dany.dev/v1",
                 "kind": "Authentik",
@@ -50,12 +65,7 @@ fn build(name: String, obj: &crd::Authentik) -> Value {
         },
         "spec": {
             "type": "ClusterIP",
-            "ports": [{
-                "name": "http",
-                "port": 80,
-                "targetPort": "http",
-                "protocol": "TCP"
-            }],
+            "ports": ports,
             "selector": labels::get_matching_labels(name.clone(), "server".to_string())
         }
     })
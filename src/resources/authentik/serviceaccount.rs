@@ -1,8 +1,9 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use k8s_openapi::api::core::v1::Secret;
+use kube::{Api, Client, ResourceExt};
 
 use crate::akapi::{
-    auth::get_valid_token,
+    auth::{get_valid_token, operator_token_secret},
     token::{CreateToken, CreateTokenBody, CreateTokenError, DeleteToken, DeleteTokenError},
     token_identifier_name,
     user::{
@@ -28,22 +29,49 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
     let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
     let ak = AkClient::new(&api_key, &instance, &ns)?;
 
-    // Attempt to create the account.
-    let result = CreateServiceAccount::send(
+    // Look up the account, it could have been deleted from Authentik since the last reconcile.
+    let users = Find::send(
         &ak,
-        CreateServiceAccountBody {
-            name: API_USER.to_string(),
-            create_group: false,
+        FindBody {
+            username: Some(API_USER.to_string()),
+            ..Default::default()
         },
     )
-    .await;
+    .await?;
 
-    match result {
-        Ok(account) => {
-            info!("Service account created with ID `{}`.", account.user_uid);
+    let user_id = match users.into_iter().find(|user| user.username == API_USER) {
+        Some(user) => user.pk,
+        None => {
+            // The token secret only exists if the account was created before.
+            let (secret_ns, secret_name) = operator_token_secret(&ns, &instance);
+            let secrets: Api<Secret> = Api::namespaced(client.clone(), &secret_ns);
+            if secrets.get_opt(&secret_name).await?.is_some() {
+                warn!(
+                    "The service account of `{}` was deleted from Authentik, recreating it.",
+                    instance
+                );
+            }
+
+            let result = CreateServiceAccount::send(
+                &ak,
+                CreateServiceAccountBody {
+                    name: API_USER.to_string(),
+                    create_group: false,
+                },
+            )
+            .await;
+
+            match result {
+                Ok(account) => {
+                    info!("Service account created with ID `{}`.", account.user_uid);
+                    account.user_pk
+                }
+                Err(CreateServiceAccountError::ExistsError) => {
+                    return Err(anyhow!("The server account exists, but was not found!"));
+                }
+                Err(e) => return Err(e.into()),
+            }
         }
-        Err(CreateServiceAccountError::ExistsError) => {}
-        Err(e) => return Err(e.into()),
     };
 
     // Delete the password token for this account if it exists.
@@ -57,23 +85,6 @@ pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
         Err(e) => return Err(e.into()),
     };
 
-    // Get the ID of the service account.
-    let mut users = Find::send(
-        &ak,
-        FindBody {
-            username: Some(API_USER.to_string()),
-            ..Default::default()
-        },
-    )
-    .await?;
-
-    let user_id = match users.pop() {
-        Some(user) => user.pk,
-        None => {
-            return Err(anyhow!("The server account was not found!"));
-        }
-    };
-
     // Create the api token if it does not exist.
     let result = CreateToken::send(
         &ak,
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Result};
+use k8s_openapi::api::batch::v1::Job;
+use kube::{
+    api::{DeleteParams, PostParams},
+    Api, Client, ResourceExt,
+};
+use serde_json::{json, Value};
+
+use crate::resources::hash_value;
+
+use super::{
+    connection::{self, Connection},
+    crd, deployment, labels,
+    status::Condition,
+};
+
+static SMTP_HASH_ANNOTATION: &str = "ak.dany.dev/smtp-hash";
+
+// Opens a connection with the same settings Authentik uses to send mails, which fails for a wrong
+// host, port, encryption or credentials.
+static TEST_COMMAND: &str =
+    "from django.core.mail import get_connection; get_connection(fail_silently=False).open()";
+
+// Test the connection to the SMTP server in a Job whenever the SMTP settings change.
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+
+    let name = format!("authentik-{}-smtp-test", instance);
+    let api: Api<Job> = Api::namespaced(client.clone(), &ns);
+    let existing = api.get_opt(&name).await?;
+
+    let hash = match settings_hash(obj) {
+        Some(hash) => hash,
+        None => {
+            // The test of earlier settings says nothing about the current ones.
+            if existing.is_some() {
+                api.delete(&name, &DeleteParams::background()).await?;
+            }
+            return Ok(());
+        }
+    };
+
+    // The Job is kept for as long as the settings don't change, as its outcome is shown in the status.
+    if let Some(job) = existing {
+        if job.annotations().get(SMTP_HASH_ANNOTATION) == Some(&hash) {
+            return Ok(());
+        }
+
+        api.delete(&name, &DeleteParams::background()).await?;
+        // The new Job can only be created once the old one is gone, which triggers a reconcile.
+        return Ok(());
+    }
+
+    let conn = connection::resolve(&obj.spec, client, &ns).await?;
+    let job = serde_json::from_value(build(instance.clone(), obj, &hash, &conn))?;
+    api.create(&PostParams::default(), &job).await?;
+
+    info!(
+        "Started testing the SMTP connection of `{}` in Job `{}`.",
+        instance, name
+    );
+
+    Ok(())
+}
+
+// The outcome of the test of the current settings, or none if the connection isn't tested.
+pub async fn condition(obj: &crd::Authentik, client: Client) -> Result<Option<Condition>> {
+    let hash = match settings_hash(obj) {
+        Some(hash) => hash,
+        None => return Ok(None),
+    };
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", obj.name_any()))?;
+
+    let name = format!("authentik-{}-smtp-test", obj.name_any());
+    let api: Api<Job> = Api::namespaced(client, &ns);
+    let status = match api.get_opt(&name).await? {
+        Some(job) if job.annotations().get(SMTP_HASH_ANNOTATION) == Some(&hash) => {
+            job.status.unwrap_or_default()
+        }
+        _ => return Ok(Some(("Unknown", "Testing", None))),
+    };
+
+    if status.succeeded.unwrap_or(0) > 0 {
+        return Ok(Some(("True", "Connected", None)));
+    }
+    if status.failed.unwrap_or(0) > 0 {
+        return Ok(Some((
+            "False",
+            "ConnectionFailed",
+            Some(format!(
+                "Failed to connect to the SMTP server, see the logs of Job `{}`.",
+                name
+            )),
+        )));
+    }
+    Ok(Some(("Unknown", "Testing", None)))
+}
+
+fn settings_hash(obj: &crd::Authentik) -> Option<String> {
+    match obj.spec.smtp.as_ref() {
+        Some(smtp) if smtp.test_connection => Some(hash_value(&json!(smtp))),
+        _ => None,
+    }
+}
+
+fn build(name: String, obj: &crd::Authentik, hash: &str, conn: &Connection) -> Value {
+    let image = obj.spec.server_image();
+
+    let mut job = json!({
+        "apiVersion": "batch/v1",
+        "kind": "Job",
+        "metadata": {
+            "name": format!("authentik-{}-smtp-test", name.clone()),
+            "labels": labels::get_labels(name.clone(), obj.spec.version(), "smtp-test".to_string()),
+            "annotations": {
+                SMTP_HASH_ANNOTATION: hash
+            },
+            "ownerReferences": [{
+                "apiVersion": "ak.dany.dev/v1",
+                "kind": "Authentik",
+                "name": name,
+                "uid": obj.uid().expect("Failed to get UID of Authentik."),
+                "controller": true,
+            }]
+        },
+        "spec": {
+            "backoffLimit": 0,
+            "template": {
+                "metadata": {
+                    "labels": labels::get_labels(name.clone(), obj.spec.version(), "smtp-test".to_string()),
+                },
+                "spec": {
+                    "restartPolicy": "Never",
+                    "automountServiceAccountToken": false,
+                    "volumes": deployment::build_volumes(&obj.spec),
+                    "containers": [{
+                        "name": "smtp-test",
+                        "image": format!("{}:{}", image.repository, image.tag),
+                        "imagePullPolicy": image.pull_policy(),
+                        "command": ["ak", "shell", "-c", TEST_COMMAND],
+                        "terminationMessagePolicy": obj.spec.termination_message_policy,
+                        "securityContext": deployment::build_security_context(&obj.spec),
+                        "volumeMounts": deployment::build_volume_mounts(&obj.spec),
+                        "env": deployment::build_env_server(&obj.spec, conn)
+                    }]
+                }
+            }
+        }
+    });
+
+    deployment::set_pod_options(&mut job["spec"]["template"]["spec"], &obj.spec);
+
+    job
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use chrono::Utc;
+use kube::{
+    api::{Patch, PatchParams},
+    Api, ResourceExt,
+};
+use serde_json::json;
+
+use super::crd;
+
+// The amount of reconcile results kept in the status.
+const HISTORY_SIZE: usize = 5;
+
+static FORCE_RECONCILE_ANNOTATION: &str = "ak.dany.dev/force-reconcile";
+
+// The status, reason and message of a condition.
+pub type Condition = (&'static str, &'static str, Option<String>);
+
+pub enum Readiness {
+    // Ready, running the given version.
+    Ready(String),
+    Migrating(String),
+    Suspended,
+}
+
+// The value of the force reconcile annotation, if it changed since the last recorded reconcile.
+pub fn forced_reconcile(obj: &crd::Authentik) -> Option<&String> {
+    let requested = obj.annotations().get(FORCE_RECONCILE_ANNOTATION)?;
+    let handled = obj
+        .status
+        .as_ref()
+        .and_then(|status| status.force_reconcile.as_ref());
+    if handled == Some(requested) {
+        return None;
+    }
+    Some(requested)
+}
+
+pub async fn record(
+    obj: &crd::Authentik,
+    api: &Api<crd::Authentik>,
+    result: &Result<Readiness>,
+    smtp: Option<Condition>,
+) -> Result<()> {
+    let event = crd::AuthentikReconcileEvent {
+        timestamp: Utc::now(),
+        generation: obj.metadata.generation,
+        result: match result {
+            Ok(_) => "Succeeded".to_string(),
+            Err(_) => "Failed".to_string(),
+        },
+        message: result.as_ref().err().map(|e| format!("{:#}", e)),
+    };
+
+    let status = obj.status.clone().unwrap_or_default();
+    let mut history = status.history.clone();
+
+    // Skip repeated outcomes, as every status update triggers another reconcile.
+    let repeated = history.last().is_some_and(|last| {
+        last.generation == event.generation
+            && last.result == event.result
+            && last.message == event.message
+    });
+    if !repeated {
+        history.push(event);
+        if history.len() > HISTORY_SIZE {
+            history.drain(..history.len() - HISTORY_SIZE);
+        }
+    }
+
+    let conditions = build_conditions(&status.conditions, result, obj.spec.suspended, smtp);
+    let conditions_changed = conditions.len() != status.conditions.len()
+        || conditions
+            .iter()
+            .zip(status.conditions.iter())
+            .any(|(new, old)| new.last_transition_time != old.last_transition_time);
+
+    let forced = forced_reconcile(obj);
+
+    // The last version that was running, which is kept while a new one is rolled out.
+    let version = match result {
+        Ok(Readiness::Ready(version)) => Some(version),
+        _ => status.version.as_ref(),
+    };
+    let version_changed = version != status.version.as_ref();
+
+    // The generation of which all parts were applied, even if it is not ready yet.
+    let observed_generation = match result {
+        Ok(_) => obj.metadata.generation,
+        Err(_) => status.observed_generation,
+    };
+    let observed_changed = observed_generation != status.observed_generation;
+
+    if repeated && !conditions_changed && forced.is_none() && !version_changed && !observed_changed
+    {
+        return Ok(());
+    }
+
+    api.patch_status(
+        &obj.name_any(),
+        &PatchParams::default(),
+        &Patch::Merge(json!({
+            "status": {
+                "history": history,
+                "conditions": conditions,
+                "forceReconcile": forced.or(status.force_reconcile.as_ref()),
+                "version": version,
+                "observedGeneration": observed_generation
+            }
+        })),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn build_conditions(
+    current: &[crd::AuthentikCondition],
+    result: &Result<Readiness>,
+    suspended: bool,
+    smtp: Option<Condition>,
+) -> Vec<crd::AuthentikCondition> {
+    let (ready, migrating) = match result {
+        Ok(Readiness::Ready(_)) => (("True", "Ready", None), Some(("False", "Migrated", None))),
+        Ok(Readiness::Migrating(message)) => (
+            ("False", "Migrating", Some(message.clone())),
+            Some(("True", "Migrating", Some(message.clone()))),
+        ),
+        // Nothing is running, so no migration can be in progress.
+        Ok(Readiness::Suspended) => (("False", "Suspended", None), None),
+        // The migration state is unknown when the reconcile failed, so it is left as is.
+        Err(e) => (("False", "ReconcileFailed", Some(format!("{:#}", e))), None),
+    };
+
+    let mut conditions = vec![set_condition(current, "Ready", ready)];
+    match migrating {
+        Some(migrating) => conditions.push(set_condition(current, "Migrating", migrating)),
+        None => conditions.extend(
+            current
+                .iter()
+                .find(|condition| condition.condition_type == "Migrating")
+                .cloned(),
+        ),
+    }
+    conditions.push(set_condition(
+        current,
+        "Suspended",
+        match suspended {
+            true => ("True", "Suspended", None),
+            false => ("False", "Running", None),
+        },
+    ));
+    // This is left out when the connection isn't tested, so an outdated outcome isn't shown.
+    if let Some(smtp) = smtp {
+        conditions.push(set_condition(current, "SmtpConnection", smtp));
+    }
+
+    conditions
+}
+
+// Build a condition, keeping the current one if nothing changed so the transition time is kept.
+fn set_condition(
+    current: &[crd::AuthentikCondition],
+    condition_type: &str,
+    (status, reason, message): (&str, &str, Option<String>),
+) -> crd::AuthentikCondition {
+    if let Some(condition) = current.iter().find(|condition| {
+        condition.condition_type == condition_type
+            && condition.status == status
+            && condition.reason == reason
+            && condition.message == message
+    }) {
+        return condition.clone();
+    }
+
+    crd::AuthentikCondition {
+        condition_type: condition_type.to_string(),
+        status: status.to_string(),
+        reason: reason.to_string(),
+        message,
+        last_transition_time: Utc::now(),
+    }
+}
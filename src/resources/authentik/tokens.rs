@@ -0,0 +1,63 @@
+use std::collections::BTreeMap;
+
+use anyhow::{anyhow, Result};
+use kube::{Client, ResourceExt};
+
+use crate::akapi::{
+    auth::get_valid_token,
+    managed_name_prefix,
+    token::{DeleteToken, DeleteTokenError, FindToken},
+    user::UserExists,
+    AkApiRoute, AkClient,
+};
+
+use super::crd;
+
+// Delete the tokens created by the operator whose user no longer exists.
+pub async fn reconcile(obj: &crd::Authentik, client: Client) -> Result<()> {
+    if !obj.spec.cleanup_orphaned_tokens {
+        return Ok(());
+    }
+
+    let instance = obj
+        .metadata
+        .name
+        .clone()
+        .ok_or(anyhow!("Missing instance name.".to_string()))?;
+    let ns = obj
+        .namespace()
+        .ok_or(anyhow!("Missing namespace `{}`.", instance.clone()))?;
+    let prefix = managed_name_prefix(&instance);
+
+    let api_key = get_valid_token(client, &ns, &instance).await?;
+    let ak = AkClient::new(&api_key, &instance, &ns)?;
+
+    let tokens = FindToken::send(&ak, ()).await?;
+    let mut users_exist = BTreeMap::new();
+
+    for token in tokens
+        .into_iter()
+        .filter(|token| token.identifier.starts_with(&prefix))
+    {
+        // Several tokens can belong to the same user, so only check each user once.
+        let exists = match users_exist.get(&token.user) {
+            Some(exists) => *exists,
+            None => {
+                let exists = UserExists::send(&ak, token.user).await?;
+                users_exist.insert(token.user, exists);
+                exists
+            }
+        };
+        if exists {
+            continue;
+        }
+
+        match DeleteToken::send(&ak, token.identifier.clone()).await {
+            Ok(_) => info!("Deleted orphaned token `{}`.", token.identifier),
+            Err(DeleteTokenError::NotFound) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(())
+}
@@ -1,12 +1,17 @@
 use anyhow::{anyhow, Result};
-use kube::{Client, ResourceExt};
+use kube::{
+    api::{Patch, PatchParams},
+    Api, Client, ResourceExt,
+};
+use serde_json::json;
 
 use crate::akapi::{
     application::{
-        CreateApplication, DeleteApplication, DeleteApplicationError, GetApplication,
-        PatchApplication,
+        CreateApplication, DeleteApplication, DeleteApplicationError, ListApplications,
+        ListApplicationsBody, PatchApplication,
     },
     auth::get_valid_token,
+    ensure::{ensure_exists, Ensured},
     provider::{FindProvider, FindProviderBody},
     types::{Application, Provider},
     AkApiRoute, AkClient,
@@ -43,20 +48,43 @@ pub async fn reconcile(obj: &crd::AuthentikApplication, client: Client) -> Resul
         ))?;
 
     let new_app = build_application(obj.spec.clone(), &provider);
-    // Get the application, create or patch depending on if it exists.
-    match GetApplication::send(&ak, obj.spec.slug.clone()).await? {
-        Some(app) => {
-            // Compare the serialized versions of the applications.
-            // The non-serialized object contains values we don't care about, and can conflict.
-            if serde_json::to_string(&app)? != serde_json::to_string(&new_app)? {
-                // There is a difference in the objects, patching it.
-                PatchApplication::send(&ak, new_app).await?;
-            }
-        }
-        None => {
-            CreateApplication::send(&ak, new_app).await?;
+    // Create the application, or patch it if it exists but differs.
+    let (_, ensured) = ensure_exists::<_, ListApplications, CreateApplication, PatchApplication>(
+        &ak,
+        || ListApplicationsBody {
+            slug_prefix: Some(obj.spec.slug.clone()),
+        },
+        |app| app.slug == obj.spec.slug,
+        new_app,
+        |_, app| app,
+    )
+    .await?;
+    if ensured != Ensured::Unchanged {
+        info!("{:?} application `{}`.", ensured, obj.spec.slug);
+    }
+
+    // Remove the applications which were created under a previous slug.
+    let slugs = vec![obj.spec.slug.clone()];
+    for slug in managed_slugs(obj) {
+        if slug != obj.spec.slug {
+            delete_application(&ak, &slug).await?;
         }
-    };
+    }
+
+    // Store the slugs in the status, so the cleanup knows what to remove.
+    if Some(&slugs) != obj.status.as_ref().map(|status| &status.managed_slugs) {
+        let api: Api<crd::AuthentikApplication> = Api::namespaced(client, &ns);
+        api.patch_status(
+            &obj.name_any(),
+            &PatchParams::default(),
+            &Patch::Merge(json!({
+                "status": {
+                    "managedSlugs": slugs
+                }
+            })),
+        )
+        .await?;
+    }
 
     Ok(())
 }
@@ -71,18 +99,33 @@ pub async fn cleanup(obj: &crd::AuthentikApplication, client: Client) -> Result<
     let api_key = get_valid_token(client.clone(), &ns, &instance).await?;
     let ak = AkClient::new(&api_key, &instance, &ns)?;
 
-    // Delete the application.
-    match DeleteApplication::send(&ak, obj.spec.slug.clone()).await {
-        Ok(_) => {
-            info!("Deleted application `{}`.", obj.spec.name);
-        }
-        Err(DeleteApplicationError::NotFound) => {}
-        Err(e) => return Err(e.into()),
-    };
+    // Delete all the applications managed by this object.
+    for slug in managed_slugs(obj) {
+        delete_application(&ak, &slug).await?;
+    }
 
     Ok(())
 }
 
+fn managed_slugs(obj: &crd::AuthentikApplication) -> Vec<String> {
+    // Objects from before the slugs were tracked only manage the current slug.
+    match obj.status.as_ref() {
+        Some(status) if !status.managed_slugs.is_empty() => status.managed_slugs.clone(),
+        _ => vec![obj.spec.slug.clone()],
+    }
+}
+
+async fn delete_application(ak: &AkClient, slug: &str) -> Result<()> {
+    match DeleteApplication::send(ak, slug.to_string()).await {
+        Ok(_) => {
+            info!("Deleted application `{}`.", slug);
+            Ok(())
+        }
+        Err(DeleteApplicationError::NotFound) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
 fn build_application(spec: crd::AuthentikApplicationSpec, provider: &Provider) -> Application {
     Application {
         pk: "".to_string(),
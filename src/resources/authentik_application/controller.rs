@@ -1,8 +1,9 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use kube::{runtime::controller::Action, Client, ResourceExt};
-use tokio::time::Duration;
+
+use crate::resources::{failed_part, requeue_interval};
 
 use super::{application, crd};
 
@@ -22,14 +23,23 @@ impl Controller {
         );
 
         // Reconcile all parts.
-        application::reconcile(&obj, self.client.clone()).await?;
+        application::reconcile(&obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "application", obj.as_ref()))?;
 
-        info!("Reconcilidation of Authentik application `{}` finished successfully, re-queued for 30 minutes.", obj.name_any());
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
+        let interval = requeue_interval(obj.as_ref());
+        info!(
+            "Reconcilidation of Authentik application `{}` finished successfully, re-queued for {} seconds.",
+            obj.name_any(),
+            interval.as_secs()
+        );
+        Ok(Action::requeue(interval))
     }
 
     pub async fn cleanup(&self, obj: Arc<crd::AuthentikApplication>) -> Result<Action> {
-        application::cleanup(obj.as_ref(), self.client.clone()).await?;
+        application::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "application", obj.as_ref()))?;
 
         Ok(Action::await_change())
     }
@@ -15,6 +15,7 @@ lazy_static! {
     group = "ak.dany.dev",
     version = "v1",
     shortname = "akapp",
+    status = "AuthentikApplicationStatus",
     namespaced
 )]
 pub struct AuthentikApplicationSpec {
@@ -34,6 +35,13 @@ pub struct AuthentikApplicationSpec {
     pub ui: AuthentikApplicationUI,
 }
 
+#[derive(Deserialize, Serialize, Clone, Debug, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthentikApplicationStatus {
+    #[serde(default)]
+    pub managed_slugs: Vec<String>,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PolicyMode {
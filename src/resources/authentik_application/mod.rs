@@ -3,7 +3,7 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use kube::{
-    api::{Api, ListParams, ResourceExt},
+    api::{Api, ResourceExt},
     runtime::{self, controller::Action, finalizer},
     Client,
 };
@@ -11,6 +11,8 @@ use tokio::{sync::Mutex, time::Duration};
 
 use crate::ReconcileError;
 
+use super::{reconcile_with_timeout, watched_api, watched_lp};
+
 mod controller;
 pub mod crd;
 
@@ -23,9 +25,9 @@ pub struct Manager;
 impl Manager {
     pub fn new(client: Client) -> BoxFuture<'static, ()> {
         let ctrlr = Controller::new(client.clone());
-        let apps = Api::<crd::AuthentikApplication>::all(client.clone());
+        let apps = watched_api::<crd::AuthentikApplication>(client.clone());
 
-        let drainer = runtime::Controller::new(apps, ListParams::default())
+        let drainer = runtime::Controller::new(apps, watched_lp())
             .run(
                 move |obj, controller| Self::reconcile(obj, controller, client.clone()),
                 move |_, e, _| Self::error_policy(e),
@@ -57,8 +59,12 @@ impl Manager {
                 let controller = controller.lock().await;
 
                 match event {
-                    finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                    finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
+                    finalizer::Event::Apply(server) => {
+                        reconcile_with_timeout(server.clone(), controller.reconcile(server)).await
+                    }
+                    finalizer::Event::Cleanup(server) => {
+                        reconcile_with_timeout(server.clone(), controller.cleanup(server)).await
+                    }
                 }
                 .map_err(|e| e.into())
             },
@@ -3,7 +3,7 @@ use std::sync::Arc;
 use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use kube::{
-    api::{Api, ListParams, ResourceExt},
+    api::{Api, ResourceExt},
     runtime::{self, controller::Action, finalizer},
     Client,
 };
@@ -18,14 +18,16 @@ use controller::Controller;
 
 use crate::ReconcileError;
 
+use super::{reconcile_with_timeout, watched_api, watched_lp};
+
 pub struct Manager;
 
 impl Manager {
     pub fn new(client: Client) -> BoxFuture<'static, ()> {
         let ctrlr = Controller::new(client.clone());
-        let users = Api::<crd::AuthentikGroup>::all(client.clone());
+        let users = watched_api::<crd::AuthentikGroup>(client.clone());
 
-        let drainer = runtime::Controller::new(users, ListParams::default())
+        let drainer = runtime::Controller::new(users, watched_lp())
             .run(
                 move |obj, controller| Self::reconcile(obj, controller, client.clone()),
                 move |_, e, _| Self::error_policy(e),
@@ -57,8 +59,12 @@ impl Manager {
                 let controller = controller.lock().await;
 
                 match event {
-                    finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                    finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
+                    finalizer::Event::Apply(server) => {
+                        reconcile_with_timeout(server.clone(), controller.reconcile(server)).await
+                    }
+                    finalizer::Event::Cleanup(server) => {
+                        reconcile_with_timeout(server.clone(), controller.cleanup(server)).await
+                    }
                 }
                 .map_err(|e| e.into())
             },
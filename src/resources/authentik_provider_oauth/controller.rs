@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use kube::{
     api::{Patch, PatchParams},
     runtime::controller::Action,
@@ -10,6 +10,8 @@ use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde_json::{json, Map};
 use tokio::time::Duration;
 
+use crate::resources::{failed_part, requeue_interval};
+
 use super::{crd, provider, secret};
 
 pub struct Controller {
@@ -42,16 +44,29 @@ impl Controller {
         }
 
         // Reconcile all parts.
-        provider::reconcile(&obj, self.client.clone()).await?;
-        secret::reconcile(&obj, self.client.clone()).await?;
-
-        info!("Reconcilidation of Authentik oauth provider `{}` finished successfully, re-queued for 30 minutes.", obj.name_any());
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
+        provider::reconcile(&obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "provider", &obj))?;
+        secret::reconcile(&obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "secret", &obj))?;
+
+        let interval = requeue_interval(&obj);
+        info!(
+            "Reconcilidation of Authentik oauth provider `{}` finished successfully, re-queued for {} seconds.",
+            obj.name_any(),
+            interval.as_secs()
+        );
+        Ok(Action::requeue(interval))
     }
 
     pub async fn cleanup(&self, obj: Arc<crd::AuthentikOAuthProvider>) -> Result<Action> {
-        secret::cleanup(obj.as_ref(), self.client.clone()).await?;
-        provider::cleanup(obj.as_ref(), self.client.clone()).await?;
+        secret::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "secret", obj.as_ref()))?;
+        provider::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "provider", obj.as_ref()))?;
 
         Ok(Action::await_change())
     }
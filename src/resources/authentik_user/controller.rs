@@ -1,8 +1,9 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
 use kube::{api::ResourceExt, runtime::controller::Action, Client};
-use tokio::time::Duration;
+
+use crate::resources::{failed_part, requeue_interval};
 
 use super::{crd, group, password, user};
 
@@ -22,18 +23,35 @@ impl Controller {
         );
 
         // Reconcile all parts.
-        user::reconcile(&obj, self.client.clone()).await?;
-        password::reconcile(&obj, self.client.clone()).await?;
-        group::reconcile(&obj, self.client.clone()).await?;
-
-        info!("Reconcilidation of Authentik user `{}` finished successfully, re-queued for 30 minutes.", obj.name_any());
-        Ok(Action::requeue(Duration::from_secs(30 * 60)))
+        user::reconcile(&obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "user", obj.as_ref()))?;
+        password::reconcile(&obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "password", obj.as_ref()))?;
+        group::reconcile(&obj, self.client.clone())
+            .await
+            .with_context(|| failed_part("reconciling", "group", obj.as_ref()))?;
+
+        let interval = requeue_interval(obj.as_ref());
+        info!(
+            "Reconcilidation of Authentik user `{}` finished successfully, re-queued for {} seconds.",
+            obj.name_any(),
+            interval.as_secs()
+        );
+        Ok(Action::requeue(interval))
     }
 
     pub async fn cleanup(&self, obj: Arc<crd::AuthentikUser>) -> Result<Action> {
-        group::cleanup(obj.as_ref(), self.client.clone()).await?;
-        password::cleanup(obj.as_ref(), self.client.clone()).await?;
-        user::cleanup(obj.as_ref(), self.client.clone()).await?;
+        group::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "group", obj.as_ref()))?;
+        password::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "password", obj.as_ref()))?;
+        user::cleanup(obj.as_ref(), self.client.clone())
+            .await
+            .with_context(|| failed_part("cleaning up", "user", obj.as_ref()))?;
 
         Ok(Action::await_change())
     }
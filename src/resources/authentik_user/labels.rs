@@ -6,6 +6,10 @@ pub fn get_labels(instance: String, component: String) -> BTreeMap<String, Strin
         "app.kubernetes.io/created-by".to_string(),
         "authentik-operator".to_string(),
     );
+    labels.insert(
+        "app.kubernetes.io/managed-by".to_string(),
+        "authentik-operator".to_string(),
+    );
 
     labels
 }
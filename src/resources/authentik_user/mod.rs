@@ -4,7 +4,7 @@ use anyhow::anyhow;
 use futures::{future::BoxFuture, FutureExt, StreamExt};
 use k8s_openapi::api::core::v1::Secret;
 use kube::{
-    api::{Api, ListParams, ResourceExt},
+    api::{Api, ResourceExt},
     runtime::{self, controller::Action, finalizer},
     Client,
 };
@@ -22,19 +22,19 @@ use controller::Controller;
 
 use crate::ReconcileError;
 
-use super::list_lp;
+use super::{list_lp, reconcile_with_timeout, watched_api, watched_lp};
 
 pub struct Manager;
 
 impl Manager {
     pub fn new(client: Client) -> BoxFuture<'static, ()> {
         let ctrlr = Controller::new(client.clone());
-        let users = Api::<crd::AuthentikUser>::all(client.clone());
+        let users = watched_api::<crd::AuthentikUser>(client.clone());
 
-        let secrets = Api::<Secret>::all(client.clone());
+        let secrets = watched_api::<Secret>(client.clone());
         let lp = list_lp("ak-user");
 
-        let drainer = runtime::Controller::new(users, ListParams::default())
+        let drainer = runtime::Controller::new(users, watched_lp())
             .owns(secrets, lp.clone())
             .run(
                 move |obj, controller| Self::reconcile(obj, controller, client.clone()),
@@ -63,8 +63,12 @@ impl Manager {
             let controller = controller.lock().await;
 
             match event {
-                finalizer::Event::Apply(server) => controller.reconcile(server).await,
-                finalizer::Event::Cleanup(server) => controller.cleanup(server).await,
+                finalizer::Event::Apply(server) => {
+                    reconcile_with_timeout(server.clone(), controller.reconcile(server)).await
+                }
+                finalizer::Event::Cleanup(server) => {
+                    reconcile_with_timeout(server.clone(), controller.cleanup(server)).await
+                }
             }
             .map_err(|e| e.into())
         })
@@ -1,4 +1,29 @@
-use kube::api::ListParams;
+use std::{fmt::Debug, sync::Arc};
+
+use anyhow::anyhow;
+use futures::Future;
+use k8s_openapi::{
+    apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition,
+    NamespaceResourceScope,
+};
+use kube::{
+    api::{ListParams, Patch, PatchParams},
+    runtime::{
+        controller::Action,
+        events::{Event, EventType, Recorder},
+    },
+    Api, Client, CustomResourceExt, Resource, ResourceExt,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio::time::{timeout, Duration};
+
+static APPLIED_HASH_ANNOTATION: &str = "ak.dany.dev/applied-hash";
+static REQUEUE_SECONDS_ANNOTATION: &str = "ak.dany.dev/requeue-seconds";
+
+// Objects are reconciled again after this long, unless the annotation gives another interval.
+static DEFAULT_REQUEUE_INTERVAL: Duration = Duration::from_secs(30 * 60);
 
 pub mod authentik;
 pub mod authentik_application;
@@ -12,8 +37,301 @@ pub use authentik_group::Manager as AuthentikGroupManager;
 pub use authentik_provider_oauth::Manager as AuthentikOAuthManager;
 pub use authentik_user::Manager as AuthentikUserManager;
 
+pub fn crds() -> Vec<CustomResourceDefinition> {
+    vec![
+        authentik::crd::Authentik::crd(),
+        authentik_application::crd::AuthentikApplication::crd(),
+        authentik_group::crd::AuthentikGroup::crd(),
+        authentik_user::crd::AuthentikUser::crd(),
+        authentik_provider_oauth::crd::AuthentikOAuthProvider::crd(),
+    ]
+}
+
+// Serialize all CRDs to a single multi-document YAML string.
+pub fn crds_yaml() -> Result<String, serde_yaml::Error> {
+    let mut yaml = String::new();
+    for crd in crds() {
+        yaml.push_str("---\n");
+        yaml.push_str(&serde_yaml::to_string(&crd)?);
+    }
+
+    Ok(yaml)
+}
+
 fn list_lp(part_of: &str) -> ListParams {
     ListParams::default().labels(
         &format!("app.kubernetes.io/created-by=authentik-operator,app.kubernetes.io/name=authentik,app.kubernetes.io/part-of={}", part_of),
     )
 }
+
+pub fn watch_namespace() -> Option<String> {
+    std::env::var("WATCH_NAMESPACE")
+        .ok()
+        .filter(|ns| !ns.is_empty())
+}
+
+// The label selector the objects have to match to be managed by this operator.
+pub fn watch_selector() -> Option<String> {
+    std::env::var("SELECTOR")
+        .ok()
+        .filter(|selector| !selector.is_empty())
+}
+
+// Watch only the objects matching the configured selector if one is set, and all objects otherwise.
+fn watched_lp() -> ListParams {
+    match watch_selector() {
+        Some(selector) => ListParams::default().labels(&selector),
+        None => ListParams::default(),
+    }
+}
+
+// The namespace to store the credentials created by the operator in, instead of alongside each instance.
+pub fn credentials_namespace() -> Option<String> {
+    std::env::var("CREDENTIALS_NAMESPACE")
+        .ok()
+        .filter(|ns| !ns.is_empty())
+}
+
+// Watch only the configured namespace if one is set, and the whole cluster otherwise.
+pub fn watched_api<K>(client: Client) -> Api<K>
+where
+    K: Resource<Scope = NamespaceResourceScope>,
+    <K as Resource>::DynamicType: Default,
+{
+    match watch_namespace() {
+        Some(ns) => Api::namespaced(client, &ns),
+        None => Api::all(client),
+    }
+}
+
+fn reconcile_timeout() -> Duration {
+    let seconds = std::env::var("RECONCILE_TIMEOUT_SECONDS")
+        .ok()
+        .and_then(|seconds| seconds.parse().ok())
+        .unwrap_or(120);
+
+    Duration::from_secs(seconds)
+}
+
+// Abort a reconcile that takes too long, so a stuck object doesn't hold up the others.
+// This is started once the lock of the controller is held, so waiting on other objects doesn't count.
+async fn reconcile_with_timeout<K>(
+    obj: Arc<K>,
+    reconcile: impl Future<Output = anyhow::Result<Action>>,
+) -> anyhow::Result<Action>
+where
+    K: Resource,
+    <K as Resource>::DynamicType: Default,
+{
+    let duration = reconcile_timeout();
+
+    match timeout(duration, reconcile).await {
+        Ok(result) => result,
+        Err(_) => Err(anyhow!(
+            "Reconcile of {} `{}` in `{}` timed out after {} seconds.",
+            K::kind(&Default::default()),
+            obj.name_any(),
+            obj.namespace().unwrap_or_default(),
+            duration.as_secs()
+        )),
+    }
+}
+
+// The interval to reconcile the object again after a successful reconcile.
+// An invalid annotation is ignored, as it shouldn't stop the object from being reconciled.
+fn requeue_interval<K>(obj: &K) -> Duration
+where
+    K: Resource,
+    <K as Resource>::DynamicType: Default,
+{
+    let value = match obj.annotations().get(REQUEUE_SECONDS_ANNOTATION) {
+        Some(value) => value,
+        None => return DEFAULT_REQUEUE_INTERVAL,
+    };
+
+    match value.trim().parse::<u64>() {
+        Ok(seconds) if seconds > 0 => Duration::from_secs(seconds),
+        _ => {
+            warn!(
+                "Ignoring the `{}` annotation of {} `{}`, as `{}` is not a positive number of seconds.",
+                REQUEUE_SECONDS_ANNOTATION,
+                K::kind(&Default::default()),
+                obj.name_any(),
+                value
+            );
+            DEFAULT_REQUEUE_INTERVAL
+        }
+    }
+}
+
+// Describe which part of which object failed, as the same parts exist for every instance.
+fn failed_part<K>(action: &str, part: &str, obj: &K) -> String
+where
+    K: Resource,
+    <K as Resource>::DynamicType: Default,
+{
+    format!(
+        "Failed {} the {} of {} `{}` in `{}`",
+        action,
+        part,
+        K::kind(&Default::default()),
+        obj.name_any(),
+        obj.namespace().unwrap_or_default()
+    )
+}
+
+// Apply the object, unless the live object already has all the values that would be set.
+// This avoids a write for every reconcile, which would otherwise trigger yet another reconcile.
+// The hash of the applied object is kept, so removing a value from it still results in an apply.
+async fn apply_if_changed<K>(api: &Api<K>, name: &str, mut desired: Value) -> anyhow::Result<()>
+where
+    K: Resource + Clone + DeserializeOwned + Serialize + Debug,
+    <K as Resource>::DynamicType: Default,
+{
+    set_applied_hash(&mut desired);
+
+    if let Some(live) = api.get_opt(name).await? {
+        if contains(&serde_json::to_value(&live)?, &desired) {
+            debug!(
+                "{} `{}` is up to date, skipping the apply.",
+                K::kind(&Default::default()),
+                name
+            );
+            return Ok(());
+        }
+    }
+
+    api.patch(
+        name,
+        &PatchParams::apply("authentik.ak-operator").force(),
+        &Patch::Apply(desired),
+    )
+    .await?;
+
+    Ok(())
+}
+
+fn set_applied_hash(desired: &mut Value) {
+    desired["metadata"]["annotations"][APPLIED_HASH_ANNOTATION] =
+        Value::String(hash_value(desired));
+}
+
+// The hashes are kept in annotations, so these have to stay the same between builds of the operator.
+// The keys of the objects are sorted, so the JSON is the same for equal values.
+fn hash_value(value: &Value) -> String {
+    format!("{:x}", Sha256::digest(value.to_string().as_bytes()))
+}
+
+// Whether all values of the desired object are present in the live object.
+// The live object has defaults and values of other managers as well, so only the desired keys are compared.
+fn contains(live: &Value, desired: &Value) -> bool {
+    match (live, desired) {
+        (_, Value::Null) => true,
+        (Value::Object(live), Value::Object(desired)) => desired.iter().all(|(key, value)| {
+            live.get(key)
+                .map_or(value.is_null(), |live| contains(live, value))
+        }),
+        (Value::Array(live), Value::Array(desired)) => {
+            live.len() == desired.len()
+                && live
+                    .iter()
+                    .zip(desired.iter())
+                    .all(|(live, desired)| contains(live, desired))
+        }
+        (live, desired) => live == desired,
+    }
+}
+
+// Publish a warning event on the object. Events are only informational, so failures are only logged.
+async fn publish_warning<K>(client: Client, obj: &K, reason: &str, note: String)
+where
+    K: Resource,
+    <K as Resource>::DynamicType: Default,
+{
+    let recorder = Recorder::new(
+        client,
+        "authentik-operator".to_string().into(),
+        obj.object_ref(&Default::default()),
+    );
+    let event = Event {
+        type_: EventType::Warning,
+        reason: reason.to_string(),
+        note: Some(note),
+        action: "Reconciling".to_string(),
+        secondary: None,
+    };
+
+    if let Err(e) = recorder.publish(event).await {
+        warn!("Failed to publish the `{}` event: {}", reason, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn contains_ignores_defaulted_live_fields() {
+        let live = json!({
+            "spec": {
+                "replicas": 1,
+                "revisionHistoryLimit": 10,
+                "template": { "spec": { "dnsPolicy": "ClusterFirst" } },
+            },
+        });
+        let desired = json!({ "spec": { "replicas": 1, "template": { "spec": {} } } });
+        assert!(contains(&live, &desired));
+    }
+
+    #[test]
+    fn contains_detects_changed_and_missing_values() {
+        let live = json!({ "spec": { "replicas": 1 } });
+        assert!(!contains(&live, &json!({ "spec": { "replicas": 2 } })));
+        assert!(!contains(&live, &json!({ "spec": { "paused": true } })));
+    }
+
+    #[test]
+    fn contains_treats_null_as_unset() {
+        let live = json!({ "spec": { "replicas": 1 } });
+        assert!(contains(&live, &json!({ "spec": { "paused": null } })));
+        assert!(contains(&live, &json!({ "spec": { "replicas": null } })));
+    }
+
+    #[test]
+    fn contains_compares_arrays_in_order() {
+        let live =
+            json!({ "ports": [{ "name": "http", "protocol": "TCP" }, { "name": "metrics" }] });
+        assert!(contains(
+            &live,
+            &json!({ "ports": [{ "name": "http" }, { "name": "metrics" }] })
+        ));
+        assert!(!contains(
+            &live,
+            &json!({ "ports": [{ "name": "metrics" }, { "name": "http" }] })
+        ));
+        assert!(!contains(&live, &json!({ "ports": [{ "name": "http" }] })));
+        assert!(!contains(
+            &live,
+            &json!({ "ports": [{ "name": "http" }, { "name": "metrics" }, { "name": "extra" }] })
+        ));
+    }
+
+    #[test]
+    fn removed_key_is_caught_by_applied_hash() {
+        let mut applied = json!({ "metadata": {}, "spec": { "replicas": 1, "paused": true } });
+        set_applied_hash(&mut applied);
+        // The live object keeps the removed value until the next apply.
+        let live = applied.clone();
+
+        let mut desired = json!({ "metadata": {}, "spec": { "replicas": 1 } });
+        assert!(contains(&live, &desired));
+        set_applied_hash(&mut desired);
+        assert!(!contains(&live, &desired));
+
+        let mut unchanged = json!({ "metadata": {}, "spec": { "replicas": 1, "paused": true } });
+        set_applied_hash(&mut unchanged);
+        assert!(contains(&live, &unchanged));
+    }
+}